@@ -0,0 +1,94 @@
+// Compares the line-by-line `search` with a `Searcher` skimming whole blocks
+// for a literal, and with one going line by line as it does for other
+// matchers. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use minigrep::matcher::Literal;
+use minigrep::{Matcher, Searcher, Span};
+
+const RUNS: usize = 5;
+
+fn main() {
+	let text = haystack(64 * 1024 * 1024);
+	let mb = text.len() as f64 / (1024.0 * 1024.0);
+	
+	// A rare query shows the scan speed, a common one the cost per match.
+	for query in ["needle", "fox"] {
+		println!("{:.0} MiB of text, searching for {:?}", mb, query);
+		
+		let (lines, time) = best_of(|| minigrep::search(query, &text).len());
+		report("search (line by line)", lines, time, mb);
+		
+		let matcher = Literal::new(query);
+		let (skimmed, time) = best_of(|| Searcher::new().count(&matcher, text.as_bytes()).unwrap());
+		report("Searcher (whole buffer)", skimmed, time, mb);
+		
+		let line_by_line = LineByLine(Literal::new(query));
+		let (searched, time) = best_of(|| Searcher::new().count(&line_by_line, text.as_bytes()).unwrap());
+		report("Searcher (line by line)", searched, time, mb);
+		
+		assert_eq!(lines, skimmed);
+		assert_eq!(lines, searched);
+	}
+}
+
+// Hides the literal from the `Searcher`, so it can't skim for it.
+struct LineByLine(Literal);
+
+impl Matcher for LineByLine {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		self.0.find_at(line, from)
+	}
+}
+
+// Lines of made-up words, with the query on about one line in ten thousand.
+fn haystack(size: usize) -> String {
+	let words = ["the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog", "needs", "to", "say"];
+	let mut text = String::with_capacity(size + 100);
+	let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+	let mut line = 0;
+	
+	while text.len() < size {
+		line += 1;
+		if line % 10_000 == 0 {
+			text.push_str("found the needle\n");
+			continue;
+		}
+		for _ in 0..10 {
+			seed ^= seed << 13;
+			seed ^= seed >> 7;
+			seed ^= seed << 17;
+			text.push_str(words[(seed % words.len() as u64) as usize]);
+			text.push(' ');
+		}
+		text.push('\n');
+	}
+	
+	text
+}
+
+fn best_of(mut f: impl FnMut() -> usize) -> (usize, Duration) {
+	let mut best = Duration::MAX;
+	let mut result = 0;
+	
+	for _ in 0..RUNS {
+		let started = Instant::now();
+		result = black_box(f());
+		best = best.min(started.elapsed());
+	}
+	
+	(result, best)
+}
+
+fn report(name: &str, lines: usize, time: Duration, mb: f64) {
+	println!(
+		"{:<24} {:>6} lines  {:>8.2} ms  {:>8.0} MiB/s",
+		name,
+		lines,
+		time.as_secs_f64() * 1000.0,
+		mb / time.as_secs_f64()
+	);
+}
+
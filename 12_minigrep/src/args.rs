@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: minigrep [OPTIONS] PATTERN [FILE...]
+       minigrep [OPTIONS] -e PATTERN... [-f FILE...] [FILE...]
+       minigrep index build [DIR]
+
+Search for PATTERN in each FILE. Directories are searched recursively and
+glob patterns such as 'src/**/*.rs' are expanded. With no FILE, or when FILE
+is '-', standard input is searched.
+
+'minigrep index build' writes an index of the files under DIR (default '.')
+to DIR/.minigrep-index, or brings an existing one up to date.
+
+Matching:
+  -E, --regex                 treat PATTERN as a regular expression
+  -e, --regexp=PATTERN        search for PATTERN; may be repeated
+  -f, --file=FILE             search for the patterns in FILE, one per line
+  -i, --ignore-case           ignore case distinctions
+  -s, --case-sensitive        match case exactly (default unless
+                              GREP_CASE_INSENSITIVE is set)
+      --case-folding=KIND     'full' (default) or 'simple' case folding
+      --locale=LOCALE         case folding rules, e.g. 'tr' for Turkish
+  -w, --word-regexp           match only whole words
+  -x, --line-regexp           match only whole lines
+      --fuzzy=NUM             match text within NUM insertions, deletions or
+                              substitutions of PATTERN
+  -v, --invert-match          select lines that do not match
+
+Output:
+  -n, --line-number           print line numbers
+  -b, --byte-offset           print the byte offset of each line
+      --column                print the column of the first match
+  -c, --count                 print the number of selected lines per file
+  -l, --files-with-matches    print only names of files with a match
+  -L, --files-without-match   print only names of files without a match
+  -A, --after-context=NUM     print NUM lines after each match
+  -B, --before-context=NUM    print NUM lines before each match
+  -C, --context=NUM           print NUM lines before and after each match
+      --json                  print results as JSON Lines
+      --color[=WHEN]          use colors: 'auto' (default), 'always' or 'never'
+
+Replacing:
+      --replace=TEXT          print lines with each match replaced by TEXT;
+                              with -E, $1 or ${name} insert capture groups
+      --in-place[=SUFFIX]     rewrite the files instead, keeping a backup with
+                              SUFFIX appended to the name if one is given
+      --dry-run               print the changes as a diff without writing
+
+Input:
+      --encoding=LABEL        read files in the given encoding, e.g. 'gbk'
+  -z, --search-zip            search inside gzip-compressed files (always on
+                              when searching directories)
+      --binary-files=TYPE     for files that look binary, 'binary' (default)
+                              only says whether they match, 'text' prints
+                              them like text, 'without-match' skips them
+  -a, --text                  same as --binary-files=text
+  -I                          same as --binary-files=without-match
+      --files0-from=FILE      also search the NUL-separated file names in FILE
+                              ('-' reads them from standard input)
+      --max-depth=NUM         descend at most NUM directories
+      --follow                follow symbolic links
+      --hidden                search hidden files and directories
+      --no-ignore             don't skip files listed in .gitignore, .ignore
+                              or the global git ignore file
+  -g, --glob=GLOB             search only files matching GLOB, or skip them
+                              if GLOB starts with '!'; may be repeated
+      --iglob=GLOB            like --glob, ignoring case
+  -t, --type=TYPE             search only files of TYPE, e.g. 'rust' or 'py'
+  -T, --type-not=TYPE         skip files of TYPE
+      --index                 skip files in directories that their index shows
+                              can't match; files changed since the index was
+                              built are always searched
+  -j, --threads=NUM           search files on NUM threads (default: one per
+                              CPU, 0 also picks that)
+
+  -h, --help                  print this help and exit
+  -V, --version               print version information and exit
+";
+
+pub enum Arg {
+	Short(char),
+	Long(String),
+	Positional(String),
+}
+
+// Splits command-line arguments into options and positional arguments.
+// Short options can be combined (`-nv`) and take their value either attached
+// (`-C3`) or from the next argument; long options take theirs after `=` or
+// from the next argument. Everything after `--` is positional.
+pub struct Parser {
+	// Remaining arguments, last one first.
+	args: Vec<String>,
+	short: Vec<char>,
+	long_value: Option<String>,
+	option: String,
+	only_positional: bool,
+}
+
+impl Parser {
+	pub fn new(args: &[String]) -> Parser {
+		Parser {
+			args: args.iter().rev().cloned().collect(),
+			short: Vec::new(),
+			long_value: None,
+			option: String::new(),
+			only_positional: false,
+		}
+	}
+	
+	pub fn next_arg(&mut self) -> Result<Option<Arg>, String> {
+		if self.long_value.is_some() {
+			return Err(format!("option '{}' doesn't allow a value", self.option));
+		}
+		if !self.short.is_empty() {
+			let c = self.short.remove(0);
+			self.option = format!("-{}", c);
+			return Ok(Some(Arg::Short(c)));
+		}
+		
+		let arg = match self.args.pop() {
+			Some(arg) => arg,
+			None => return Ok(None),
+		};
+		
+		if self.only_positional || arg == "-" || !arg.starts_with('-') {
+			Ok(Some(Arg::Positional(arg)))
+		} else if arg == "--" {
+			self.only_positional = true;
+			self.next_arg()
+		} else if let Some(long) = arg.strip_prefix("--") {
+			let (name, value) = match long.split_once('=') {
+				Some((name, value)) => (name, Some(value.to_string())),
+				None => (long, None),
+			};
+			self.option = format!("--{}", name);
+			self.long_value = value;
+			Ok(Some(Arg::Long(name.to_string())))
+		} else {
+			self.short = arg.chars().skip(1).collect();
+			self.next_arg()
+		}
+	}
+	
+	// The value for the option just returned by `next_arg`.
+	pub fn value(&mut self) -> Result<String, String> {
+		if let Some(value) = self.long_value.take() {
+			return Ok(value);
+		}
+		if !self.short.is_empty() {
+			return Ok(self.short.drain(..).collect());
+		}
+		
+		self.args
+			.pop()
+			.ok_or_else(|| format!("option '{}' requires a value", self.option))
+	}
+	
+	// A value that may only be given attached, as in `--color=always`.
+	pub fn optional_value(&mut self) -> Option<String> {
+		self.long_value.take()
+	}
+	
+	pub fn parse_value<T: FromStr>(&mut self) -> Result<T, String> {
+		let value = self.value()?;
+		value.parse().map_err(|_| self.invalid(&value))
+	}
+	
+	pub fn invalid(&self, value: &str) -> String {
+		format!("invalid value '{}' for option '{}'", value, self.option)
+	}
+	
+	pub fn unknown(&self) -> String {
+		format!("unknown option '{}' (see --help)", self.option)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn collect(args: &[&str]) -> Result<Vec<String>, String> {
+		let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+		let mut parser = Parser::new(&args);
+		let mut seen = Vec::new();
+		
+		while let Some(arg) = parser.next_arg()? {
+			seen.push(match arg {
+				Arg::Short('C') => format!("C={}", parser.value()?),
+				Arg::Long(name) if name == "max-depth" => format!("max-depth={}", parser.value()?),
+				Arg::Short(c) => c.to_string(),
+				Arg::Long(name) => name,
+				Arg::Positional(value) => format!("[{}]", value),
+			});
+		}
+		Ok(seen)
+	}
+	
+	#[test]
+	fn splits_options() {
+		assert_eq!(
+			vec!["n", "v", "C=3", "max-depth=2", "C=1", "[-]", "[x]", "[-i]"],
+			collect(&["-nvC3", "--max-depth=2", "-C", "1", "-", "x", "--", "-i"]).unwrap()
+		);
+	}
+	
+	#[test]
+	fn reports_bad_values() {
+		assert!(collect(&["--regex=yes"]).is_err());
+		assert!(collect(&["x", "--max-depth"]).is_err());
+	}
+}
@@ -0,0 +1,133 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+	#[default]
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorChoice {
+	pub fn from_name(name: &str) -> Option<ColorChoice> {
+		match name {
+			"auto" => Some(ColorChoice::Auto),
+			"always" => Some(ColorChoice::Always),
+			"never" => Some(ColorChoice::Never),
+			_ => None,
+		}
+	}
+}
+
+// SGR parameters (the part between `ESC[` and `m`) for each kind of output.
+// An empty string leaves that kind uncolored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colors {
+	pub matched: String,
+	pub path: String,
+	pub line_number: String,
+	pub byte_offset: String,
+	pub separator: String,
+}
+
+impl Default for Colors {
+	fn default() -> Colors {
+		Colors {
+			matched: String::from("01;31"),
+			path: String::from("35"),
+			line_number: String::from("32"),
+			byte_offset: String::from("32"),
+			separator: String::from("36"),
+		}
+	}
+}
+
+impl Colors {
+	pub fn plain() -> Colors {
+		Colors {
+			matched: String::new(),
+			path: String::new(),
+			line_number: String::new(),
+			byte_offset: String::new(),
+			separator: String::new(),
+		}
+	}
+	
+	// Picks the colors for a run: none unless `choice` and the environment
+	// allow them, otherwise the defaults adjusted by `MINIGREP_COLORS`.
+	pub fn from_env(choice: ColorChoice) -> Colors {
+		let enabled = match choice {
+			ColorChoice::Always => true,
+			ColorChoice::Never => false,
+			ColorChoice::Auto => {
+				let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+				!no_color && io::stdout().is_terminal()
+			}
+		};
+		
+		if !enabled {
+			return Colors::plain();
+		}
+		match env::var("MINIGREP_COLORS") {
+			Ok(spec) => Colors::parse(&spec),
+			Err(_) => Colors::default(),
+		}
+	}
+	
+	// Parses a spec in the style of `GREP_COLORS`, e.g. `mt=01;32:fn=34:se=`.
+	// Unknown keys and malformed values are ignored.
+	pub fn parse(spec: &str) -> Colors {
+		let mut colors = Colors::default();
+		
+		for entry in spec.split(':') {
+			let (key, value) = match entry.split_once('=') {
+				Some(pair) => pair,
+				None => continue,
+			};
+			if !value.chars().all(|c| c.is_ascii_digit() || c == ';') {
+				continue;
+			}
+			
+			let value = value.to_string();
+			match key {
+				"mt" | "ms" => colors.matched = value,
+				"fn" => colors.path = value,
+				"ln" => colors.line_number = value,
+				"bn" => colors.byte_offset = value,
+				"se" => colors.separator = value,
+				_ => {}
+			}
+		}
+		
+		colors
+	}
+	
+	pub fn paint(sgr: &str, text: &str) -> String {
+		if sgr.is_empty() || text.is_empty() {
+			text.to_string()
+		} else {
+			format!("\x1b[{}m\x1b[K{}\x1b[m\x1b[K", sgr, text)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn parses_spec() {
+		let colors = Colors::parse("mt=01;32:fn=:ln=bad:xx=1");
+		
+		assert_eq!("01;32", colors.matched);
+		assert_eq!("", colors.path);
+		assert_eq!("32", colors.line_number);
+	}
+	
+	#[test]
+	fn paints_text() {
+		assert_eq!("\x1b[35m\x1b[Kpoem.txt\x1b[m\x1b[K", Colors::paint("35", "poem.txt"));
+		assert_eq!("poem.txt", Colors::paint("", "poem.txt"));
+	}
+}
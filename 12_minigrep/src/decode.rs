@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_16BE, UTF_16LE};
+use flate2::bufread::MultiGzDecoder;
+
+// How a gzip stream starts, including the DEFLATE method byte.
+const GZIP_MAGIC: [u8; 3] = [0x1F, 0x8B, 0x08];
+
+// What to do with files that look binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BinaryFiles {
+	// Search them, but only say whether they match.
+	#[default]
+	Binary,
+	// Search and print them like text.
+	Text,
+	// Treat them as having no matches.
+	WithoutMatch,
+}
+
+impl BinaryFiles {
+	pub fn from_name(name: &str) -> Option<BinaryFiles> {
+		match name {
+			"binary" => Some(BinaryFiles::Binary),
+			"text" => Some(BinaryFiles::Text),
+			"without-match" => Some(BinaryFiles::WithoutMatch),
+			_ => None,
+		}
+	}
+}
+
+// Whether the first block of a file looks binary: it has a NUL byte or
+// isn't UTF-8. A character cut off at the end of the block doesn't count.
+pub fn is_binary(block: &[u8]) -> bool {
+	if block.contains(&0) {
+		return true;
+	}
+	match std::str::from_utf8(block) {
+		Ok(_) => false,
+		Err(e) => e.error_len().is_some(),
+	}
+}
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+	bytes.starts_with(&GZIP_MAGIC)
+}
+
+// Opens a file for streaming as text. Without an explicit encoding the file
+// must be UTF-8, unless it starts with a UTF-16 byte order mark. Text in
+// other encodings is transcoded to UTF-8 as it is read, with undecodable
+// bytes replaced. With `decompress`, gzip-compressed input is decompressed
+// first.
+pub fn open(
+	path: &Path,
+	encoding: Option<&'static Encoding>,
+	decompress: bool,
+) -> io::Result<Box<dyn BufRead>> {
+	wrap(BufReader::new(File::open(path)?), encoding, decompress)
+}
+
+// Like `open`, for input that is already being read, such as stdin.
+pub fn wrap<R>(
+	mut reader: R,
+	encoding: Option<&'static Encoding>,
+	decompress: bool,
+) -> io::Result<Box<dyn BufRead>>
+where
+	R: BufRead + 'static,
+{
+	if decompress && is_gzip(reader.fill_buf()?) {
+		// Files joined with `cat` hold several gzip members in a row.
+		transcode(BufReader::new(MultiGzDecoder::new(reader)), encoding)
+	} else {
+		transcode(reader, encoding)
+	}
+}
+
+fn transcode<R>(mut reader: R, encoding: Option<&'static Encoding>) -> io::Result<Box<dyn BufRead>>
+where
+	R: BufRead + 'static,
+{
+	let encoding = match encoding {
+		Some(encoding) => Some(encoding),
+		None => match Encoding::for_bom(reader.fill_buf()?) {
+			Some((encoding, _)) if encoding == UTF_16LE || encoding == UTF_16BE => Some(encoding),
+			Some((_, bom_len)) => {
+				reader.consume(bom_len);
+				None
+			}
+			None => None,
+		},
+	};
+	
+	match encoding {
+		Some(encoding) => {
+			let decoder = encoding.new_decoder_with_bom_removal();
+			Ok(Box::new(BufReader::new(DecodeReader { inner: reader, decoder, done: false })))
+		}
+		None => Ok(Box::new(reader)),
+	}
+}
+
+// Adapts a reader in some other encoding into one producing UTF-8.
+struct DecodeReader<R> {
+	inner: R,
+	decoder: Decoder,
+	done: bool,
+}
+
+impl<R: BufRead> Read for DecodeReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		while !self.done && !buf.is_empty() {
+			let src = self.inner.fill_buf()?;
+			let last = src.is_empty();
+			let (result, read, written, _) = self.decoder.decode_to_utf8(src, buf, last);
+			self.inner.consume(read);
+			
+			if last && result == CoderResult::InputEmpty {
+				self.done = true;
+			}
+			if written > 0 {
+				return Ok(written);
+			}
+		}
+		
+		Ok(0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+	
+	fn decode(bytes: Vec<u8>, encoding: Option<&'static Encoding>) -> io::Result<String> {
+		let mut text = String::new();
+		wrap(io::Cursor::new(bytes), encoding, false)?.read_to_string(&mut text)?;
+		Ok(text)
+	}
+	
+	#[test]
+	fn sniffs_byte_order_marks() {
+		let le = vec![0xFF, 0xFE, b'h', 0, b'i', 0];
+		let be = vec![0xFE, 0xFF, 0, b'h', 0, b'i'];
+		let utf8 = vec![0xEF, 0xBB, 0xBF, b'h', b'i'];
+		
+		assert_eq!("hi", decode(le, None).unwrap());
+		assert_eq!("hi", decode(be, None).unwrap());
+		assert_eq!("hi", decode(utf8, None).unwrap());
+	}
+	
+	#[test]
+	fn explicit_encodings() {
+		let latin1 = Encoding::for_label(b"latin1");
+		let gbk = Encoding::for_label(b"gbk");
+		
+		assert_eq!("café", decode(vec![b'c', b'a', b'f', 0xE9], latin1).unwrap());
+		assert_eq!("中文", decode(vec![0xD6, 0xD0, 0xCE, 0xC4], gbk).unwrap());
+		assert!(decode(vec![0xD6, 0xD0], None).is_err());
+	}
+	
+	#[test]
+	fn detects_binary_blocks() {
+		assert!(!is_binary("plain text, café".as_bytes()));
+		// The block ends in the middle of `é`.
+		assert!(!is_binary(&"café".as_bytes()[..4]));
+		assert!(is_binary(b"ELF\x02\x01\x00\x00"));
+		assert!(is_binary(&[b'a', 0xFF, b'b']));
+	}
+	
+	#[test]
+	fn streams_transcoded_text() {
+		let path = std::env::temp_dir().join("minigrep-decode-stream.txt");
+		let mut bytes = vec![0xFF, 0xFE];
+		for unit in "one\r\ntwo\r\n".encode_utf16() {
+			bytes.extend(unit.to_le_bytes());
+		}
+		fs::write(&path, bytes).unwrap();
+		
+		let lines: Vec<String> = open(&path, None, false).unwrap().lines().map(|l| l.unwrap()).collect();
+		assert_eq!(vec!["one", "two"], lines);
+	}
+	
+	#[test]
+	fn decompresses_gzip() {
+		use flate2::write::GzEncoder;
+		use flate2::Compression;
+		use std::io::Write;
+		
+		let mut bytes = Vec::new();
+		for part in ["one\n", "two\n"] {
+			let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+			encoder.write_all(part.as_bytes()).unwrap();
+			bytes.extend(encoder.finish().unwrap());
+		}
+		
+		let read = |decompress| {
+			let mut reader = wrap(io::Cursor::new(bytes.clone()), None, decompress).unwrap();
+			let mut text = Vec::new();
+			reader.read_to_end(&mut text).unwrap();
+			text
+		};
+		assert_eq!(b"one\ntwo\n".to_vec(), read(true));
+		assert_eq!(bytes, read(false));
+	}
+}
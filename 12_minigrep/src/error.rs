@@ -0,0 +1,105 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum MinigrepError {
+	// Bad command-line arguments.
+	Usage(String),
+	// A file or directory that couldn't be read.
+	Io { path: PathBuf, source: io::Error },
+	// A file that isn't valid text in the expected encoding.
+	Encoding { path: PathBuf },
+	// A query that isn't a valid pattern.
+	Pattern(String),
+}
+
+impl MinigrepError {
+	pub fn io(path: impl Into<PathBuf>, source: io::Error) -> MinigrepError {
+		let path = path.into();
+		if source.kind() == io::ErrorKind::InvalidData {
+			MinigrepError::Encoding { path }
+		} else {
+			MinigrepError::Io { path, source }
+		}
+	}
+}
+
+impl fmt::Display for MinigrepError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			MinigrepError::Usage(message) => write!(f, "{}", message),
+			MinigrepError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+			MinigrepError::Encoding { path } => {
+				write!(f, "{}: stream did not contain valid UTF-8 (try --encoding)", path.display())
+			}
+			MinigrepError::Pattern(e) => write!(f, "invalid pattern: {}", e),
+		}
+	}
+}
+
+impl Error for MinigrepError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			MinigrepError::Io { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
+
+impl From<String> for MinigrepError {
+	fn from(message: String) -> MinigrepError {
+		MinigrepError::Usage(message)
+	}
+}
+
+impl From<regex::Error> for MinigrepError {
+	fn from(e: regex::Error) -> MinigrepError {
+		MinigrepError::Pattern(e.to_string())
+	}
+}
+
+// What a run found, used to pick the exit status the way grep does.
+#[derive(Debug, Default)]
+pub struct Summary {
+	pub files_searched: usize,
+	pub files_matched: usize,
+	pub matched_lines: usize,
+	pub errors: usize,
+}
+
+impl Summary {
+	// 0 if anything matched, 1 if nothing did, 2 if there was an error.
+	pub fn exit_code(&self) -> i32 {
+		if self.errors > 0 {
+			2
+		} else if self.matched_lines > 0 {
+			0
+		} else {
+			1
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn exit_codes() {
+		let matched = Summary { matched_lines: 3, ..Default::default() };
+		let failed = Summary { matched_lines: 3, errors: 1, ..Default::default() };
+		
+		assert_eq!(0, matched.exit_code());
+		assert_eq!(1, Summary::default().exit_code());
+		assert_eq!(2, failed.exit_code());
+	}
+	
+	#[test]
+	fn invalid_data_is_an_encoding_error() {
+		let e = io::Error::new(io::ErrorKind::InvalidData, "bad");
+		
+		assert!(matches!(MinigrepError::io("a.txt", e), MinigrepError::Encoding { .. }));
+	}
+}
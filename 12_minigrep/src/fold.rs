@@ -0,0 +1,218 @@
+// Unicode case folding used by case-insensitive matching.
+//
+// Full folding may turn one character into several (`ß` folds to `ss`), while
+// simple folding always maps a character to exactly one. Folding is derived
+// from the standard library's case mappings: taking the lowercase of the
+// uppercase of a character gives its case fold, which also brings final
+// sigma, the long s and compatibility characters like the Kelvin sign
+// together with their ordinary forms.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Folding {
+	Simple,
+	#[default]
+	Full,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Locale {
+	#[default]
+	Root,
+	// Turkish and Azerbaijani, where `I` pairs with dotless `ı` and `İ` with `i`.
+	Turkic,
+}
+
+impl Locale {
+	pub fn from_tag(tag: &str) -> Option<Locale> {
+		let language = tag.split(['_', '-', '.']).next().unwrap_or("");
+		match language.to_ascii_lowercase().as_str() {
+			"" | "c" | "posix" | "en" | "root" => Some(Locale::Root),
+			"tr" | "az" => Some(Locale::Turkic),
+			_ => None,
+		}
+	}
+}
+
+// The folding of a single character, which is never more than a few chars.
+struct Folded {
+	chars: [char; 4],
+	len: usize,
+}
+
+impl Folded {
+	fn push(&mut self, c: char) {
+		self.chars[self.len] = c;
+		self.len += 1;
+	}
+	
+	fn as_slice(&self) -> &[char] {
+		&self.chars[..self.len]
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaseFolder {
+	pub folding: Folding,
+	pub locale: Locale,
+}
+
+impl CaseFolder {
+	pub fn new(folding: Folding, locale: Locale) -> CaseFolder {
+		CaseFolder { folding, locale }
+	}
+	
+	pub fn fold_str(&self, s: &str) -> Vec<char> {
+		let mut folded = Vec::with_capacity(s.len());
+		for c in s.chars() {
+			folded.extend_from_slice(self.fold_char(c).as_slice());
+		}
+		folded
+	}
+	
+	// Folds `s` into a string, along with the offset in it where each char of
+	// `s` starts folded, paired with that char's own offset. The end of `s` is
+	// included.
+	pub fn fold_with_offsets(&self, s: &str) -> (String, Vec<(usize, usize)>) {
+		let mut folded = String::with_capacity(s.len());
+		let mut offsets = Vec::with_capacity(s.len() + 1);
+		for (i, c) in s.char_indices() {
+			offsets.push((folded.len(), i));
+			folded.extend(self.fold_char(c).as_slice());
+		}
+		offsets.push((folded.len(), s.len()));
+		(folded, offsets)
+	}
+	
+	// Folds `c` to a single char with simple folding, whatever `folding` says.
+	pub fn fold_one(&self, c: char) -> char {
+		let simple = CaseFolder { folding: Folding::Simple, locale: self.locale };
+		simple.fold_char(c).chars[0]
+	}
+	
+	fn fold_char(&self, c: char) -> Folded {
+		let mut out = Folded { chars: ['\0'; 4], len: 0 };
+		
+		if c.is_ascii() {
+			out.push(match (self.locale, c) {
+				(Locale::Turkic, 'I') => 'ı',
+				_ => c.to_ascii_lowercase(),
+			});
+			return out;
+		}
+		if self.locale == Locale::Turkic && (c == 'İ' || c == 'ı') {
+			out.push(if c == 'İ' { 'i' } else { 'ı' });
+			return out;
+		}
+		
+		let mut upper = c.to_uppercase();
+		match self.folding {
+			Folding::Simple => {
+				let single = if upper.len() == 1 { upper.next() } else { None };
+				let lower = single.map(|u| u.to_lowercase()).filter(|l| l.len() == 1);
+				match lower {
+					Some(mut lower) => out.push(lower.next().unwrap()),
+					None => {
+						let mut lower = c.to_lowercase();
+						out.push(if lower.len() == 1 { lower.next().unwrap() } else { c });
+					}
+				}
+			}
+			// The capital sharp s lowercases to `ß`, which itself folds to
+			// `ss`, so its mappings alone don't bring the three together.
+			Folding::Full if c == 'ẞ' => {
+				out.push('s');
+				out.push('s');
+			}
+			Folding::Full => {
+				for u in upper {
+					for l in u.to_lowercase() {
+						if out.len == out.chars.len() {
+							break;
+						}
+						out.push(l);
+					}
+				}
+			}
+		}
+		
+		out
+	}
+	
+	// Finds the first place at or after byte `from` in `line` whose folding
+	// equals `query`, which must already be folded. Matches always start and
+	// end on character boundaries of `line`.
+	pub fn find(&self, query: &[char], line: &str, from: usize) -> Option<(usize, usize)> {
+		if query.is_empty() {
+			return Some((from, from));
+		}
+		
+		for (start, _) in line[from..].char_indices() {
+			let start = from + start;
+			let mut matched = 0;
+			
+			for (i, c) in line[start..].char_indices() {
+				let folded = self.fold_char(c);
+				let folded = folded.as_slice();
+				if matched + folded.len() > query.len() || query[matched..matched + folded.len()] != *folded {
+					break;
+				}
+				matched += folded.len();
+				if matched == query.len() {
+					return Some((start, start + i + c.len_utf8()));
+				}
+			}
+		}
+		
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn finds(folder: CaseFolder, query: &str, line: &str) -> bool {
+		folder.find(&folder.fold_str(query), line, 0).is_some()
+	}
+	
+	#[test]
+	fn full_folding() {
+		let folder = CaseFolder::default();
+		
+		assert!(finds(folder, "STRASSE", "Straße"));
+		assert!(finds(folder, "ΣΟΦΟΣ", "σοφος"));
+		assert!(finds(folder, "σοφοσ", "ΣΟΦΟΣ"));
+		assert!(finds(folder, "ẞ", "groß"));
+		assert!(finds(folder, "ẞ", "GROSS"));
+		assert!(finds(folder, "ß", "GROẞ"));
+		assert!(finds(folder, "SS", "GROẞ"));
+		assert_eq!(Some((4, 6)), folder.find(&folder.fold_str("ss"), "a Maße", 0));
+	}
+	
+	#[test]
+	fn simple_folding() {
+		let folder = CaseFolder::new(Folding::Simple, Locale::Root);
+		
+		assert!(!finds(folder, "STRASSE", "Straße"));
+		assert!(finds(folder, "ẞ", "ß"));
+		assert!(finds(folder, "K", "\u{212A}"));
+	}
+	
+	#[test]
+	fn turkic_locale() {
+		let folder = CaseFolder::new(Folding::Full, Locale::Turkic);
+		
+		assert!(finds(folder, "ISPARTA", "ısparta"));
+		assert!(!finds(folder, "ISPARTA", "isparta"));
+		assert!(finds(folder, "İstanbul", "istanbul"));
+	}
+	
+	#[test]
+	fn match_spans_original_text() {
+		let folder = CaseFolder::default();
+		let query = folder.fold_str("strasse");
+		
+		assert_eq!(Some((4, 11)), folder.find(&query, "die STRAßE", 0));
+		assert_eq!(None, folder.find(&folder.fold_str("s"), "ß", 0));
+	}
+}
@@ -0,0 +1,106 @@
+// Approximate substring matching: finds the parts of a line that are within
+// a number of insertions, deletions or substitutions of a query, using the
+// dynamic programming method of Sellers where a match may start anywhere.
+
+use crate::fold::CaseFolder;
+use crate::Span;
+
+pub struct Fuzzy {
+	query: Vec<char>,
+	max_distance: usize,
+	// Set for case-insensitive matching, which folds one char at a time.
+	folder: Option<CaseFolder>,
+}
+
+#[derive(Clone, Copy)]
+struct Cell {
+	distance: usize,
+	start: usize,
+}
+
+impl Fuzzy {
+	pub fn new(query: &str, max_distance: usize, folder: Option<CaseFolder>) -> Fuzzy {
+		let query = query.chars().map(|c| fold(folder, c)).collect();
+		Fuzzy { query, max_distance, folder }
+	}
+	
+	// The leftmost match ending at or after `from`, extended for as long as
+	// that doesn't make the distance worse. The span's `distance` is the number of
+	// edits needed.
+	pub fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		let m = self.query.len();
+		
+		// Column for the empty prefix of the text: matching the first `i`
+		// query chars costs `i` deletions.
+		let mut prev: Vec<Cell> = (0..=m).map(|i| Cell { distance: i, start: from }).collect();
+		let mut cur = prev.clone();
+		let mut best: Option<Span> = None;
+		if m <= self.max_distance {
+			best = Some(Span::fuzzy(from, from, m));
+		}
+		
+		for (i, c) in line[from..].char_indices() {
+			let end = from + i + c.len_utf8();
+			let c = fold(self.folder, c);
+			
+			cur[0] = Cell { distance: 0, start: end };
+			for q in 1..=m {
+				let cost = if self.query[q - 1] == c { 0 } else { 1 };
+				let diagonal = Cell { distance: prev[q - 1].distance + cost, start: prev[q - 1].start };
+				let skip_query = Cell { distance: cur[q - 1].distance + 1, start: cur[q - 1].start };
+				let skip_text = Cell { distance: prev[q].distance + 1, start: prev[q].start };
+				
+				let mut cell = diagonal;
+				for other in [skip_query, skip_text] {
+					if other.distance < cell.distance {
+						cell = other;
+					}
+				}
+				cur[q] = cell;
+			}
+			
+			let cell = cur[m];
+			match best {
+				Some(span) if cell.distance > span.distance => return best,
+				Some(_) => best = Some(Span::fuzzy(cell.start, end, cell.distance)),
+				None if cell.distance <= self.max_distance => {
+					best = Some(Span::fuzzy(cell.start, end, cell.distance));
+				}
+				None => {}
+			}
+			std::mem::swap(&mut prev, &mut cur);
+		}
+		
+		best
+	}
+}
+
+fn fold(folder: Option<CaseFolder>, c: char) -> char {
+	match folder {
+		Some(folder) => folder.fold_one(c),
+		None => c,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn finds_typos() {
+		let fuzzy = Fuzzy::new("nobody", 1, None);
+		
+		assert_eq!(Some(Span::fuzzy(4, 9, 1)), fuzzy.find_at("I'm nbody!", 0));
+		assert_eq!(Some(Span::fuzzy(4, 10, 0)), fuzzy.find_at("I'm nobody!", 0));
+		assert_eq!(Some(Span::fuzzy(4, 10, 1)), fuzzy.find_at("I'm nobady!", 0));
+		assert_eq!(None, fuzzy.find_at("I'm nbdy!", 0));
+	}
+	
+	#[test]
+	fn ignores_case() {
+		let fuzzy = Fuzzy::new("Frog", 1, Some(CaseFolder::default()));
+		
+		assert_eq!(Some(Span::fuzzy(5, 9, 1)), fuzzy.find_at("like FROQ", 0));
+		assert_eq!(None, Fuzzy::new("Frog", 1, None).find_at("like FROQ", 0));
+	}
+}
@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+
+use crate::walk::{self, WalkOptions};
+use crate::MinigrepError;
+
+enum Token {
+	Char(char),
+	Any,
+	Star,
+	Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+enum Segment {
+	AnyDirs,
+	Component(Vec<Token>),
+}
+
+pub struct Glob {
+	segments: Vec<Segment>,
+	ignore_case: bool,
+	// Whether wildcards match a leading dot, as they do in ignore files.
+	match_dot: bool,
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+	pattern.contains(['*', '?', '['])
+}
+
+impl Glob {
+	pub fn new(pattern: &str) -> Glob {
+		let segments = pattern
+			.split('/')
+			.filter(|part| !part.is_empty())
+			.map(|part| {
+				if part == "**" {
+					Segment::AnyDirs
+				} else {
+					Segment::Component(parse_component(part))
+				}
+			})
+			.collect();
+		
+		Glob { segments, ignore_case: false, match_dot: false }
+	}
+	
+	pub fn ignore_case(mut self, yes: bool) -> Glob {
+		self.ignore_case = yes;
+		self
+	}
+	
+	pub fn match_dot(mut self) -> Glob {
+		self.match_dot = true;
+		self
+	}
+	
+	pub fn matches(&self, path: &str) -> bool {
+		let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+		self.match_segments(&self.segments, &parts)
+	}
+	
+	pub fn matches_path(&self, path: &Path) -> bool {
+		let parts: Vec<String> = path
+			.components()
+			.map(|c| c.as_os_str().to_string_lossy().into_owned())
+			.collect();
+		let parts: Vec<&str> = parts.iter().map(|part| part.as_str()).collect();
+		self.match_segments(&self.segments, &parts)
+	}
+	
+	fn match_segments(&self, segments: &[Segment], parts: &[&str]) -> bool {
+		match segments.first() {
+			None => parts.is_empty(),
+			Some(Segment::AnyDirs) => {
+				(0..=parts.len()).any(|skip| self.match_segments(&segments[1..], &parts[skip..]))
+			}
+			Some(Segment::Component(tokens)) => match parts.first() {
+				Some(part) => {
+					let chars: Vec<char> = part.chars().collect();
+					self.match_component(tokens, &chars, true)
+						&& self.match_segments(&segments[1..], &parts[1..])
+				}
+				None => false,
+			},
+		}
+	}
+	
+	fn match_component(&self, tokens: &[Token], chars: &[char], start: bool) -> bool {
+		// Like the shell, wildcards do not match a leading dot.
+		let hidden = start && !self.match_dot && chars.first() == Some(&'.');
+		
+		match tokens.first() {
+			None => chars.is_empty(),
+			Some(Token::Star) => {
+				if hidden {
+					return false;
+				}
+				(0..=chars.len()).any(|skip| self.match_component(&tokens[1..], &chars[skip..], false))
+			}
+			Some(token) => match chars.first() {
+				Some(&c) => {
+					let ok = match token {
+						Token::Char(expected) => c == *expected || (self.ignore_case && same_letter(c, *expected)),
+						Token::Any => !hidden,
+						Token::Class { negated, ranges } => {
+							let in_class = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+							let found = in_class(c) || (self.ignore_case && other_cases(c).any(in_class));
+							!hidden && found != *negated
+						}
+						Token::Star => unreachable!(),
+					};
+					ok && self.match_component(&tokens[1..], &chars[1..], false)
+				}
+				None => false,
+			},
+		}
+	}
+	
+	fn max_depth(&self) -> Option<usize> {
+		if self.segments.iter().any(|s| matches!(s, Segment::AnyDirs)) {
+			None
+		} else {
+			Some(self.segments.len())
+		}
+	}
+}
+
+// Expands a shell-style pattern such as `src/**/*.rs` into the files it names.
+pub fn expand(pattern: &str, errors: &mut Vec<MinigrepError>) -> Vec<PathBuf> {
+	let parts: Vec<&str> = pattern.split('/').collect();
+	let literal = parts.iter().take_while(|part| !is_glob(part)).count();
+	
+	let base = if literal == 0 {
+		PathBuf::from(".")
+	} else {
+		let mut base = parts[..literal].join("/");
+		if base.is_empty() {
+			base.push('/');
+		}
+		PathBuf::from(base)
+	};
+	let glob = Glob::new(&parts[literal..].join("/"));
+	
+	let options = WalkOptions {
+		max_depth: glob.max_depth(),
+		follow_links: false,
+		hidden: true,
+		..Default::default()
+	};
+	
+	let mut files = Vec::new();
+	for file in walk::walk(&base, &options, errors) {
+		let relative = file.strip_prefix(&base).unwrap_or(&file);
+		if glob.matches_path(relative) {
+			if literal == 0 {
+				files.push(relative.to_path_buf());
+			} else {
+				files.push(file);
+			}
+		}
+	}
+	
+	files
+}
+
+fn parse_component(part: &str) -> Vec<Token> {
+	let mut tokens = Vec::new();
+	let mut chars = part.chars().peekable();
+	
+	while let Some(c) = chars.next() {
+		match c {
+			'*' => tokens.push(Token::Star),
+			'?' => tokens.push(Token::Any),
+			'\\' => tokens.push(Token::Char(chars.next().unwrap_or('\\'))),
+			'[' => {
+				let rest: String = chars.clone().collect();
+				match parse_class(&rest) {
+					Some((token, used)) => {
+						tokens.push(token);
+						for _ in 0..used {
+							chars.next();
+						}
+					}
+					None => tokens.push(Token::Char('[')),
+				}
+			}
+			_ => tokens.push(Token::Char(c)),
+		}
+	}
+	
+	tokens
+}
+
+// Parses the body of a `[...]` class, returning the token and how many chars it used.
+fn parse_class(rest: &str) -> Option<(Token, usize)> {
+	let chars: Vec<char> = rest.chars().collect();
+	let mut i = 0;
+	let negated = matches!(chars.first(), Some('!') | Some('^'));
+	if negated {
+		i += 1;
+	}
+	
+	let mut ranges = Vec::new();
+	let mut first = true;
+	while i < chars.len() {
+		let c = chars[i];
+		if c == ']' && !first {
+			return Some((Token::Class { negated, ranges }, i + 1));
+		}
+		first = false;
+		
+		if i + 2 < chars.len() && chars[i + 1] == '-' && chars[i + 2] != ']' {
+			ranges.push((c, chars[i + 2]));
+			i += 3;
+		} else {
+			ranges.push((c, c));
+			i += 1;
+		}
+	}
+	
+	None
+}
+
+fn same_letter(a: char, b: char) -> bool {
+	a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn other_cases(c: char) -> impl Iterator<Item = char> {
+	c.to_lowercase().chain(c.to_uppercase()).filter(move |&other| other != c)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn matches_wildcards() {
+		assert!(Glob::new("*.rs").matches("lib.rs"));
+		assert!(!Glob::new("*.rs").matches("lib.rs.bak"));
+		assert!(Glob::new("file?.[ch]").matches("file1.c"));
+		assert!(!Glob::new("file?.[!ch]").matches("file1.c"));
+		assert!(!Glob::new("*").matches(".hidden"));
+		assert!(Glob::new("*").match_dot().matches(".hidden"));
+	}
+	
+	#[test]
+	fn matches_ignoring_case() {
+		assert!(!Glob::new("*.RS").matches("lib.rs"));
+		assert!(Glob::new("*.RS").ignore_case(true).matches("lib.rs"));
+		assert!(Glob::new("[A-C]*").ignore_case(true).matches("build.rs"));
+	}
+	
+	#[test]
+	fn matches_any_dirs() {
+		let glob = Glob::new("src/**/*.rs");
+		assert!(glob.matches("src/lib.rs"));
+		assert!(glob.matches("src/a/b/walk.rs"));
+		assert!(!glob.matches("tests/lib.rs"));
+	}
+}
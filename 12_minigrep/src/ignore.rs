@@ -0,0 +1,199 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::glob::Glob;
+
+// Ignore files read in each directory of a walk. Rules in `.ignore` take
+// precedence over those in `.gitignore`.
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+// File types for `--type`, as a name and the globs matching its files.
+const FILE_TYPES: &[(&str, &[&str])] = &[
+	("c", &["*.c", "*.h"]),
+	("cpp", &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"]),
+	("css", &["*.css"]),
+	("go", &["*.go"]),
+	("html", &["*.html", "*.htm"]),
+	("java", &["*.java"]),
+	("js", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+	("json", &["*.json"]),
+	("md", &["*.md", "*.markdown"]),
+	("py", &["*.py", "*.pyi"]),
+	("rb", &["*.rb"]),
+	("rust", &["*.rs"]),
+	("sh", &["*.sh", "*.bash"]),
+	("toml", &["*.toml"]),
+	("ts", &["*.ts", "*.tsx"]),
+	("txt", &["*.txt"]),
+	("yaml", &["*.yaml", "*.yml"]),
+];
+
+pub fn file_type(name: &str) -> Option<&'static [&'static str]> {
+	FILE_TYPES.iter().find(|(type_name, _)| *type_name == name).map(|(_, globs)| *globs)
+}
+
+struct Rule {
+	glob: Glob,
+	negated: bool,
+	dir_only: bool,
+}
+
+// The rules of one ignore file, in gitignore syntax. Paths are matched
+// relative to the directory the rules apply to.
+#[derive(Default)]
+pub struct Rules {
+	rules: Vec<Rule>,
+}
+
+impl Rules {
+	pub fn parse(contents: &str) -> Rules {
+		let mut rules = Rules::default();
+		for line in contents.lines() {
+			rules.add(line, false);
+		}
+		rules
+	}
+	
+	pub fn from_file(path: &Path) -> io::Result<Rules> {
+		Ok(Rules::parse(&fs::read_to_string(path)?))
+	}
+	
+	// Adds a rule; blank lines and comments are skipped. A pattern without
+	// a slash, other than a trailing one, matches at any depth.
+	pub fn add(&mut self, line: &str, ignore_case: bool) {
+		let line = line.trim_end_matches('\r').trim_end_matches(' ');
+		if line.is_empty() || line.starts_with('#') {
+			return;
+		}
+		
+		let (negated, pattern) = match line.strip_prefix('!') {
+			Some(rest) => (true, rest),
+			None => (false, line.strip_prefix('\\').unwrap_or(line)),
+		};
+		let (dir_only, pattern) = match pattern.strip_suffix('/') {
+			Some(rest) => (true, rest),
+			None => (false, pattern),
+		};
+		if pattern.is_empty() {
+			return;
+		}
+		
+		let glob = if pattern.contains('/') {
+			Glob::new(pattern.trim_start_matches('/'))
+		} else {
+			Glob::new(&format!("**/{}", pattern))
+		};
+		
+		self.rules.push(Rule { glob: glob.ignore_case(ignore_case).match_dot(), negated, dir_only });
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+	
+	// Whether the last rule matching `path` ignores it (`Some(true)`) or
+	// re-includes it with `!` (`Some(false)`).
+	pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+		self.rules
+			.iter()
+			.rev()
+			.find(|rule| (is_dir || !rule.dir_only) && rule.glob.matches_path(path))
+			.map(|rule| !rule.negated)
+	}
+}
+
+// Which files a walk yields beyond what ignore files allow: `--glob` and
+// `--iglob` patterns, where a leading `!` excludes, and `--type` filters.
+// When include patterns are given, only files matching one are searched.
+#[derive(Default)]
+pub struct Filter {
+	globs: Rules,
+	has_includes: bool,
+	types: Vec<Glob>,
+	types_not: Vec<Glob>,
+}
+
+impl Filter {
+	pub fn add_glob(&mut self, pattern: &str, ignore_case: bool) {
+		// The rules ignore what they match, so the sense is turned around.
+		match pattern.strip_prefix('!') {
+			Some(exclude) => self.globs.add(exclude, ignore_case),
+			None => {
+				self.globs.add(&format!("!{}", pattern), ignore_case);
+				self.has_includes = true;
+			}
+		}
+	}
+	
+	// Callers check the name with `file_type` first.
+	pub fn add_type(&mut self, name: &str, negated: bool) {
+		let globs = file_type(name).unwrap_or_default().iter().map(|glob| Glob::new(glob));
+		if negated {
+			self.types_not.extend(globs);
+		} else {
+			self.types.extend(globs);
+		}
+	}
+	
+	// Whether the walk skips `path`, given relative to the walk's root.
+	// Directories are only skipped by exclude patterns.
+	pub fn skips(&self, path: &Path, is_dir: bool) -> bool {
+		match self.globs.matched(path, is_dir) {
+			Some(ignored) => return ignored,
+			None if is_dir => return false,
+			None if self.has_includes => return true,
+			None => {}
+		}
+		
+		let name = Path::new(path.file_name().unwrap_or_default());
+		if !self.types.is_empty() && !self.types.iter().any(|glob| glob.matches_path(name)) {
+			return true;
+		}
+		self.types_not.iter().any(|glob| glob.matches_path(name))
+	}
+}
+
+// The user's global ignore file, where git keeps it by default.
+pub fn global_ignore_file() -> Option<PathBuf> {
+	let config = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+		Some(dir) => PathBuf::from(dir),
+		None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+	};
+	Some(config.join("git").join("ignore"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn gitignore_rules() {
+		let rules = Rules::parse("# build output\ntarget/\n*.log\n!keep.log\n/docs/*.html\n");
+		
+		assert_eq!(Some(true), rules.matched(Path::new("target"), true));
+		assert_eq!(None, rules.matched(Path::new("target"), false));
+		assert_eq!(Some(true), rules.matched(Path::new("a/b/.debug.log"), false));
+		assert_eq!(Some(false), rules.matched(Path::new("a/keep.log"), false));
+		assert_eq!(Some(true), rules.matched(Path::new("docs/index.html"), false));
+		assert_eq!(None, rules.matched(Path::new("src/docs/index.html"), false));
+	}
+	
+	#[test]
+	fn filters_by_glob_and_type() {
+		let mut filter = Filter::default();
+		filter.add_glob("!vendor", false);
+		filter.add_type("rust", false);
+		
+		assert!(filter.skips(Path::new("vendor"), true));
+		assert!(!filter.skips(Path::new("src"), true));
+		assert!(!filter.skips(Path::new("src/lib.rs"), false));
+		assert!(filter.skips(Path::new("README.md"), false));
+		
+		let mut filter = Filter::default();
+		filter.add_glob("*.TOML", true);
+		assert!(!filter.skips(Path::new("Cargo.toml"), false));
+		assert!(filter.skips(Path::new("src/lib.rs"), false));
+	}
+}
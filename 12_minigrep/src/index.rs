@@ -0,0 +1,500 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+use regex_syntax::hir::{Hir, HirKind};
+
+use crate::fold::{CaseFolder, Folding, Locale};
+use crate::walk::{self, WalkOptions};
+use crate::{Config, MinigrepError, OutputMode};
+
+// The index of a directory is kept in this file at its top.
+pub const INDEX_FILE: &str = ".minigrep-index";
+
+const MAGIC: &[u8] = b"minigrep index 1\n";
+
+// Stored in place of a trigram count for files that must always be searched.
+const UNINDEXED: u32 = u32::MAX;
+
+// What is known about one file: the modification time and size it had when
+// it was read, and the trigrams of its contents. Files the trigrams can't
+// describe, such as compressed ones, have none and are always searched.
+struct Entry {
+	modified: (u64, u32),
+	size: u64,
+	trigrams: Option<Vec<u32>>,
+}
+
+// The trigrams of every file under a directory, by path relative to it. The
+// bytes are indexed with ASCII letters lowercased, so one index serves
+// searches with and without `-i`.
+#[derive(Default)]
+pub struct Index {
+	files: BTreeMap<String, Entry>,
+}
+
+impl Index {
+	pub fn load(dir: &Path) -> io::Result<Index> {
+		let bytes = fs::read(dir.join(INDEX_FILE))?;
+		let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "not a minigrep index, rebuild it");
+		let mut input = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+		let mut index = Index::default();
+		
+		let count = read_u32(&mut input).ok_or_else(invalid)?;
+		for _ in 0..count {
+			let (name, entry) = read_entry(&mut input).ok_or_else(invalid)?;
+			index.files.insert(name, entry);
+		}
+		
+		Ok(index)
+	}
+	
+	pub fn save(&self, dir: &Path) -> io::Result<()> {
+		let mut bytes = MAGIC.to_vec();
+		bytes.extend((self.files.len() as u32).to_le_bytes());
+		for (name, entry) in &self.files {
+			write_entry(&mut bytes, name, entry);
+		}
+		
+		// Write the whole index before replacing the old one.
+		let path = dir.join(INDEX_FILE);
+		let temp = dir.join(format!("{}.{}.tmp", INDEX_FILE, std::process::id()));
+		fs::write(&temp, bytes)?;
+		fs::rename(&temp, path).inspect_err(|_| {
+			let _ = fs::remove_file(&temp);
+		})
+	}
+	
+	// Whether `path`, relative to the indexed directory, may contain a match.
+	// Files that changed since they were indexed always may.
+	fn may_match(&self, path: &Path, meta: &fs::Metadata, query: &Query) -> bool {
+		let entry = match path.to_str().and_then(|name| self.files.get(name)) {
+			Some(entry) => entry,
+			None => return true,
+		};
+		if entry.size != meta.len() || Some(entry.modified) != modified(meta) {
+			return true;
+		}
+		
+		match &entry.trigrams {
+			Some(trigrams) => query.may_match(trigrams),
+			None => true,
+		}
+	}
+}
+
+// What each pattern needs to find in a file: all of its trigrams. A file
+// can match if it has every trigram of some pattern. `None` means any file
+// can match, such as when a pattern is too short to have trigrams.
+pub struct Query {
+	patterns: Option<Vec<Vec<u32>>>,
+}
+
+impl Query {
+	pub fn new(config: &Config, patterns: &[String]) -> Query {
+		// Files without a match still print something in these modes.
+		let lists_all = matches!(
+			config.output_mode,
+			OutputMode::Count | OutputMode::FilesWithoutMatch | OutputMode::Json
+		);
+		if lists_all || config.invert_match || config.fuzzy.is_some() || config.encoding.is_some() {
+			return Query { patterns: None };
+		}
+		
+		let mut required = Vec::new();
+		for pattern in patterns {
+			let runs = if config.regex {
+				match regex_syntax::parse(pattern) {
+					Ok(hir) => required_literals(&hir),
+					Err(_) => Vec::new(),
+				}
+			} else {
+				vec![pattern.as_bytes().to_vec()]
+			};
+			
+			let mut trigrams: Vec<u32> = runs.iter().flat_map(|run| trigrams(run)).collect();
+			if !config.case_sensitive {
+				trigrams.retain(|&trigram| trigram.to_be_bytes()[1..].iter().all(|b| !can_fold_to(*b)));
+			}
+			if trigrams.is_empty() {
+				return Query { patterns: None };
+			}
+			trigrams.sort_unstable();
+			trigrams.dedup();
+			required.push(trigrams);
+		}
+		
+		Query { patterns: Some(required) }
+	}
+	
+	pub fn is_everything(&self) -> bool {
+		self.patterns.is_none()
+	}
+	
+	fn may_match(&self, trigrams: &[u32]) -> bool {
+		match &self.patterns {
+			Some(patterns) => patterns
+				.iter()
+				.any(|required| required.iter().all(|trigram| trigrams.binary_search(trigram).is_ok())),
+			None => true,
+		}
+	}
+}
+
+// Drops the files under `dir` that an index rules out for `query`. The index
+// is the one of `dir` or else of the nearest directory above it.
+pub fn filter(
+	dir: &Path,
+	files: Vec<PathBuf>,
+	query: &Query,
+	errors: &mut Vec<MinigrepError>,
+) -> Vec<PathBuf> {
+	if query.is_everything() {
+		return files;
+	}
+	let root = match dir.ancestors().find(|root| root_of(root).join(INDEX_FILE).is_file()) {
+		Some(root) => root,
+		None => {
+			let e = io::Error::new(io::ErrorKind::NotFound, "no index, run 'minigrep index build' first");
+			errors.push(MinigrepError::io(dir, e));
+			return files;
+		}
+	};
+	let index = match Index::load(root_of(root)) {
+		Ok(index) => index,
+		Err(e) => {
+			errors.push(MinigrepError::io(root_of(root).join(INDEX_FILE), e));
+			return files;
+		}
+	};
+	
+	files
+		.into_iter()
+		.filter(|file| {
+			let relative = file.strip_prefix(root).unwrap_or(file);
+			match fs::metadata(file) {
+				Ok(meta) => index.may_match(relative, &meta, query),
+				Err(_) => true,
+			}
+		})
+		.collect()
+}
+
+// Builds or refreshes the index of `dir`. Files whose modification time and
+// size are unchanged keep their entries; only the others are read again.
+// Returns the number of files indexed and how many of them were read.
+pub fn build(dir: &Path, errors: &mut Vec<MinigrepError>) -> Result<(usize, usize), MinigrepError> {
+	// A missing or broken index is built from scratch.
+	let old = Index::load(dir).unwrap_or_default();
+	let options = WalkOptions { ignore_files: true, ..Default::default() };
+	let mut index = Index::default();
+	let mut read = 0;
+	
+	for file in walk::walk(dir, &options, errors) {
+		let relative = file.strip_prefix(dir).unwrap_or(&file);
+		let name = match relative.to_str() {
+			Some(name) => name.to_string(),
+			// Files missing from the index are always searched.
+			None => continue,
+		};
+		let meta = match fs::metadata(&file) {
+			Ok(meta) => meta,
+			Err(e) => {
+				errors.push(MinigrepError::io(&file, e));
+				continue;
+			}
+		};
+		let modified = match modified(&meta) {
+			Some(modified) => modified,
+			None => continue,
+		};
+		
+		if let Some(entry) = old.files.get(&name) {
+			if entry.modified == modified && entry.size == meta.len() {
+				index.files.insert(name, Entry { trigrams: entry.trigrams.clone(), ..*entry });
+				continue;
+			}
+		}
+		
+		match fs::read(&file) {
+			Ok(bytes) => {
+				read += 1;
+				let entry = Entry { modified, size: meta.len(), trigrams: file_trigrams(&bytes) };
+				index.files.insert(name, entry);
+			}
+			Err(e) => errors.push(MinigrepError::io(&file, e)),
+		}
+	}
+	
+	index.save(dir).map_err(|e| MinigrepError::io(dir.join(INDEX_FILE), e))?;
+	Ok((index.files.len(), read))
+}
+
+// The directory to index when `args` are exactly `minigrep index build
+// [DIR]`. Anything else is a search, even one for the word "index".
+pub fn build_command(args: &[String]) -> Option<&str> {
+	match args {
+		[_, index, build] if index == "index" && build == "build" => Some("."),
+		[_, index, build, dir] if index == "index" && build == "build" => Some(dir),
+		_ => None,
+	}
+}
+
+// Runs `minigrep index build [DIR]`.
+pub fn command(dir: &str) -> Result<i32, MinigrepError> {
+	let mut errors = Vec::new();
+	let (indexed, read) = build(Path::new(dir), &mut errors)?;
+	for e in &errors {
+		eprintln!("minigrep: {}", e);
+	}
+	println!("indexed {} files in {} ({} read, {} unchanged)", indexed, dir, read, indexed - read);
+	
+	Ok(if errors.is_empty() { 0 } else { 2 })
+}
+
+// The last ancestor of a relative path is empty; it stands for `.`.
+fn root_of(dir: &Path) -> &Path {
+	if dir.as_os_str().is_empty() {
+		Path::new(".")
+	} else {
+		dir
+	}
+}
+
+// Literal strings that every match of `hir` contains.
+fn required_literals(hir: &Hir) -> Vec<Vec<u8>> {
+	let mut runs = Vec::new();
+	let mut current = Vec::new();
+	collect_literals(hir, &mut runs, &mut current);
+	runs.push(current);
+	runs
+}
+
+// Extends `current` with literal text that must follow it, and moves it to
+// `runs` once something else may come between.
+fn collect_literals(hir: &Hir, runs: &mut Vec<Vec<u8>>, current: &mut Vec<u8>) {
+	match hir.kind() {
+		HirKind::Literal(literal) => current.extend_from_slice(&literal.0),
+		HirKind::Capture(capture) => collect_literals(&capture.sub, runs, current),
+		HirKind::Concat(subs) => {
+			for sub in subs {
+				collect_literals(sub, runs, current);
+			}
+		}
+		// Assertions take up no text.
+		HirKind::Empty | HirKind::Look(_) => {}
+		HirKind::Repetition(repetition) if repetition.min > 0 => {
+			runs.push(std::mem::take(current));
+			collect_literals(&repetition.sub, runs, current);
+			runs.push(std::mem::take(current));
+		}
+		_ => runs.push(std::mem::take(current)),
+	}
+}
+
+// Whether case folding can produce `byte` from some other character, such as
+// the Kelvin sign folding to `k` or `ŉ` to `ʼn`. Trigrams with such bytes
+// say nothing about case-insensitive matches.
+fn can_fold_to(byte: u8) -> bool {
+	!byte.is_ascii() || fold_targets()[byte.to_ascii_lowercase() as usize]
+}
+
+// The ASCII that folding any non-ASCII character gives, under every kind of
+// folding and locale a search may use.
+fn fold_targets() -> &'static [bool; 128] {
+	static TARGETS: OnceLock<[bool; 128]> = OnceLock::new();
+	TARGETS.get_or_init(|| {
+		let mut targets = [false; 128];
+		let mut buf = [0; 4];
+		let folders = [Folding::Full, Folding::Simple]
+			.map(|folding| [Locale::Root, Locale::Turkic].map(|locale| CaseFolder::new(folding, locale)));
+		// Characters without case mappings fold to themselves.
+		let cased = ('\u{80}'..=char::MAX).filter(|&c| !c.to_uppercase().eq([c]) || !c.to_lowercase().eq([c]));
+		for c in cased {
+			for folder in folders.iter().flatten() {
+				for folded in folder.fold_str(c.encode_utf8(&mut buf)) {
+					if folded.is_ascii() {
+						targets[folded.to_ascii_lowercase() as usize] = true;
+					}
+				}
+			}
+		}
+		targets
+	})
+}
+
+fn trigrams(bytes: &[u8]) -> Vec<u32> {
+	bytes
+		.windows(3)
+		.map(|w| u32::from_be_bytes([0, w[0], w[1], w[2]].map(|b| b.to_ascii_lowercase())))
+		.collect()
+}
+
+// Compressed and UTF-16 files are searched as different bytes than the ones
+// on disk, so their trigrams aren't stored.
+fn file_trigrams(bytes: &[u8]) -> Option<Vec<u32>> {
+	let magic = [[0x1F, 0x8B], [0xFF, 0xFE], [0xFE, 0xFF]];
+	if magic.iter().any(|magic| bytes.starts_with(magic)) {
+		return None;
+	}
+	let mut trigrams = trigrams(bytes);
+	trigrams.sort_unstable();
+	trigrams.dedup();
+	Some(trigrams)
+}
+
+fn modified(meta: &fs::Metadata) -> Option<(u64, u32)> {
+	let since_epoch = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+	Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+fn write_entry(bytes: &mut Vec<u8>, name: &str, entry: &Entry) {
+	bytes.extend((name.len() as u32).to_le_bytes());
+	bytes.extend(name.as_bytes());
+	bytes.extend(entry.modified.0.to_le_bytes());
+	bytes.extend(entry.modified.1.to_le_bytes());
+	bytes.extend(entry.size.to_le_bytes());
+	match &entry.trigrams {
+		Some(trigrams) => {
+			bytes.extend((trigrams.len() as u32).to_le_bytes());
+			for trigram in trigrams {
+				bytes.extend(trigram.to_le_bytes());
+			}
+		}
+		None => bytes.extend(UNINDEXED.to_le_bytes()),
+	}
+}
+
+fn read_entry(input: &mut &[u8]) -> Option<(String, Entry)> {
+	let len = read_u32(input)? as usize;
+	let name = String::from_utf8(take(input, len)?.to_vec()).ok()?;
+	let modified = (read_u64(input)?, read_u32(input)?);
+	let size = read_u64(input)?;
+	
+	let count = read_u32(input)?;
+	let trigrams = if count == UNINDEXED {
+		None
+	} else {
+		let bytes = take(input, count as usize * 4)?;
+		Some(bytes.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+	};
+	
+	Some((name, Entry { modified, size, trigrams }))
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+	if input.len() < len {
+		return None;
+	}
+	let (head, rest) = input.split_at(len);
+	*input = rest;
+	Some(head)
+}
+
+fn read_u32(input: &mut &[u8]) -> Option<u32> {
+	Some(u32::from_le_bytes(take(input, 4)?.try_into().ok()?))
+}
+
+fn read_u64(input: &mut &[u8]) -> Option<u64> {
+	Some(u64::from_le_bytes(take(input, 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn query(config: Config, pattern: &str) -> Query {
+		Query::new(&config, &[pattern.to_string()])
+	}
+	
+	#[test]
+	fn literals_required_by_regex() {
+		let hir = regex_syntax::parse(r"fn (foo|bar)\(\w+: usize\)").unwrap();
+		let mut runs = required_literals(&hir);
+		runs.retain(|run| !run.is_empty());
+		
+		assert_eq!(vec![b"fn ".to_vec(), b"(".to_vec(), b": usize)".to_vec()], runs);
+	}
+	
+	#[test]
+	fn narrows_by_trigrams() {
+		let contents = file_trigrams(b"let Needle = 1;").unwrap();
+		let exact = || Config { case_sensitive: true, ..Default::default() };
+		
+		assert!(query(exact(), "Needle").may_match(&contents));
+		assert!(!query(exact(), "needle = 2").may_match(&contents));
+		assert!(query(Config::default(), "NEEDLE").may_match(&contents));
+		assert!(query(Config::default(), "ab").is_everything());
+	}
+	
+	#[test]
+	fn never_drops_a_matching_file() {
+		let dir = std::env::temp_dir().join("minigrep-index-folding");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		let lines = ["ŉob", "\u{212A}elvin", "ﬁle", "STRAßE", "ſtop", "İstanbul", "ısparta", "plain text"];
+		let files: Vec<PathBuf> = lines
+			.iter()
+			.enumerate()
+			.map(|(i, line)| {
+				let file = dir.join(format!("{}.txt", i));
+				fs::write(&file, line).unwrap();
+				file
+			})
+			.collect();
+		build(&dir, &mut Vec::new()).unwrap();
+		
+		let patterns = ["ʼnob", "kelvin", "file", "strasse", "STOP", "istanbul", "ISPARTA", "plain", "n.b"];
+		for folding in [Folding::Full, Folding::Simple] {
+			for locale in [Locale::Root, Locale::Turkic] {
+				for (case_sensitive, regex) in [(true, false), (false, false), (false, true)] {
+					let config = Config { case_sensitive, regex, case_folding: folding, locale, ..Default::default() };
+					for pattern in patterns {
+						let pattern = vec![pattern.to_string()];
+						let matcher = crate::matcher::from_config(&config, &pattern).unwrap();
+						let kept = filter(&dir, files.clone(), &Query::new(&config, &pattern), &mut Vec::new());
+						for (file, line) in files.iter().zip(lines) {
+							if matcher.is_match(line) {
+								assert!(kept.contains(file), "{:?} dropped {} for {:?}", pattern, line, config.locale);
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+	
+	#[test]
+	fn build_command_only() {
+		let args = |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+		
+		assert_eq!(Some("."), build_command(&args(&["minigrep", "index", "build"])));
+		assert_eq!(Some("src"), build_command(&args(&["minigrep", "index", "build", "src"])));
+		assert_eq!(None, build_command(&args(&["minigrep", "index"])));
+		assert_eq!(None, build_command(&args(&["minigrep", "index", "a.txt"])));
+		assert_eq!(None, build_command(&args(&["minigrep", "index", "build", "a.txt", "b.txt"])));
+	}
+	
+	#[test]
+	fn refreshes_changed_files() {
+		let dir = std::env::temp_dir().join("minigrep-index-tests");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("a.txt"), "alpha").unwrap();
+		fs::write(dir.join("b.txt"), "beta").unwrap();
+		
+		assert_eq!((2, 2), build(&dir, &mut Vec::new()).unwrap());
+		assert_eq!((2, 0), build(&dir, &mut Vec::new()).unwrap());
+		
+		fs::write(dir.join("b.txt"), "gamma, longer").unwrap();
+		assert_eq!((2, 1), build(&dir, &mut Vec::new()).unwrap());
+		
+		let config = Config { case_sensitive: true, ..Default::default() };
+		let files = vec![dir.join("a.txt"), dir.join("b.txt")];
+		let files = filter(&dir, files, &query(config, "gamma"), &mut Vec::new());
+		assert_eq!(vec![dir.join("b.txt")], files);
+	}
+}
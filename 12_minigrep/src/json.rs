@@ -0,0 +1,159 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{Line, Summary};
+
+// A JSON object serialized field by field.
+pub struct JsonObject {
+	buf: String,
+}
+
+impl JsonObject {
+	pub fn new() -> JsonObject {
+		JsonObject { buf: String::from("{") }
+	}
+	
+	// Every output line is an event object whose `type` field names it:
+	// `begin`, `match`, `context`, `end` or `summary`.
+	pub fn event(kind: &str) -> JsonObject {
+		let mut object = JsonObject::new();
+		object.string("type", kind);
+		object
+	}
+	
+	pub fn string(&mut self, key: &str, value: &str) -> &mut JsonObject {
+		self.key(key);
+		push_string(&mut self.buf, value);
+		self
+	}
+	
+	pub fn number(&mut self, key: &str, value: impl std::fmt::Display) -> &mut JsonObject {
+		self.key(key);
+		write!(self.buf, "{}", value).unwrap();
+		self
+	}
+	
+	// Writes the already-serialized JSON `value` under `key`.
+	pub fn raw(&mut self, key: &str, value: &str) -> &mut JsonObject {
+		self.key(key);
+		self.buf.push_str(value);
+		self
+	}
+	
+	pub fn finish(&mut self) -> String {
+		let mut buf = std::mem::take(&mut self.buf);
+		buf.push('}');
+		buf
+	}
+	
+	fn key(&mut self, key: &str) {
+		if self.buf.len() > 1 {
+			self.buf.push(',');
+		}
+		push_string(&mut self.buf, key);
+		self.buf.push(':');
+	}
+}
+
+pub fn begin(path: &Path) -> String {
+	JsonObject::event("begin").string("path", &path.to_string_lossy()).finish()
+}
+
+// `patterns` is used to say which pattern produced each submatch. Fuzzy
+// searches also give the edit distance of every submatch.
+pub fn line(path: &Path, line: &Line, patterns: &[String], fuzzy: bool) -> String {
+	let (kind, m) = match line {
+		Line::Match(m) => ("match", m),
+		Line::Context(m) => ("context", m),
+	};
+	
+	let mut submatches = String::from("[");
+	for (i, span) in m.spans.iter().enumerate() {
+		if i > 0 {
+			submatches.push(',');
+		}
+		let mut submatch = JsonObject::new();
+		submatch
+			.string("match", &m.line[span.start..span.end])
+			.number("start", span.start)
+			.number("end", span.end);
+		if let Some(pattern) = patterns.get(span.pattern) {
+			submatch.string("pattern", pattern);
+		}
+		if fuzzy {
+			submatch.number("distance", span.distance);
+		}
+		let submatch = submatch.finish();
+		submatches.push_str(&submatch);
+	}
+	submatches.push(']');
+	
+	JsonObject::event(kind)
+		.string("path", &path.to_string_lossy())
+		.number("line_number", m.line_number)
+		.number("absolute_offset", m.byte_offset)
+		.string("line", m.line)
+		.raw("submatches", &submatches)
+		.finish()
+}
+
+pub fn end(path: &Path, matched_lines: usize) -> String {
+	JsonObject::event("end")
+		.string("path", &path.to_string_lossy())
+		.number("matched_lines", matched_lines)
+		.finish()
+}
+
+pub fn summary(summary: &Summary, elapsed: Duration) -> String {
+	JsonObject::event("summary")
+		.number("elapsed_secs", elapsed.as_secs_f64())
+		.number("files_searched", summary.files_searched)
+		.number("files_matched", summary.files_matched)
+		.number("matched_lines", summary.matched_lines)
+		.number("errors", summary.errors)
+		.finish()
+}
+
+impl Default for JsonObject {
+	fn default() -> JsonObject {
+		JsonObject::new()
+	}
+}
+
+fn push_string(buf: &mut String, s: &str) {
+	buf.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => buf.push_str("\\\""),
+			'\\' => buf.push_str("\\\\"),
+			'\n' => buf.push_str("\\n"),
+			'\r' => buf.push_str("\\r"),
+			'\t' => buf.push_str("\\t"),
+			c if (c as u32) < 0x20 => write!(buf, "\\u{:04x}", c as u32).unwrap(),
+			c => buf.push(c),
+		}
+	}
+	buf.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Match, Span};
+	
+	#[test]
+	fn match_event() {
+		let m = Match {
+			line_number: 2,
+			byte_offset: 6,
+			line: "say \"hi\"\t",
+			spans: vec![Span::for_pattern(5, 7, 1)],
+		};
+		
+		assert_eq!(
+			r#"{"type":"match","path":"a.txt","line_number":2,"absolute_offset":6,"line":"say \"hi\"\t","submatches":[{"match":"hi","start":5,"end":7,"pattern":"h."}]}"#,
+			line(Path::new("a.txt"), &Line::Match(m), &[String::from("say"), String::from("h.")], false)
+		);
+	}
+}
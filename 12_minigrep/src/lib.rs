@@ -1,106 +1,934 @@
-use std::env;
-use std::error::Error;
-use std::fs;
-
-pub struct Config {
-	pub query: String,
-	pub filename: String,
-	pub case_sensitive: bool,
-}
-
-impl Config {
-	pub fn new(args: &[String]) -> Result<Config, &str> {
-		if args.len() < 3 {
-			return Err("Too few arguments");
-		}
-		
-		let query = args[1].clone();
-		let filename = args[2].clone();
-		
-		let case_sensitive = env::var("GREP_CASE_INSENSITIVE").is_err();
-		
-		Ok(Config { query, filename, case_sensitive })
-	}
-}
-
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-	let contents = fs::read_to_string(config.filename)?;
-	
-	let results = if config.case_sensitive {
-		search(&config.query, &contents)
-	} else {
-		search_case_insensitive(&config.query, &contents)
-	};
-	
-	//println!("With content:\n{}", contents);
-	for line in results {
-		println!("{}", line);
-	}
-	
-	Ok(())
-}
-
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-	let mut result = Vec::new();
-	
-	for line in contents.lines() {
-		if line.contains(query) {
-			result.push(line);
-		}
-	}
-	
-	result
-}
-
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-	let query = query.to_lowercase();
-	let mut result = Vec::new();
-	
-	for line in contents.lines() {
-		if line.to_lowercase().contains(&query) {
-			result.push(line);
-		}
-	}
-	
-	result
-}
-
-#[cfg(test)]
-mod tests {
-	use super::*;
-	
-	#[test]
-	fn on_result() {
-		let query = "duct";
-		let contents = "\
-Rust:
-safe, fast, productive.
-pick three.";
-		
-		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
-	}
-	
-	#[test]
-	fn case_sensitive() {
-		let query = "duct";
-		let contents = "\
-Rust:
-safe, fast, productive.
-pick three.
-Duct type.";
-		
-		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
-	}
-	
-	#[test]
-	fn case_insensitive() {
-		let query = "rUsT";
-		let contents = "\
-Rust:
-safe, fast, productive.
-pick three.
-Trust me.";
-		
-		assert_eq!(vec!["Rust:", "Trust me."], search_case_insensitive(query, contents));
-	}
-}
\ No newline at end of file
+use std::env;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use encoding_rs::Encoding;
+use regex::Regex;
+
+pub mod args;
+pub mod color;
+pub mod decode;
+pub mod error;
+pub mod fold;
+pub mod fuzzy;
+pub mod glob;
+pub mod ignore;
+pub mod index;
+pub mod json;
+pub mod matcher;
+pub mod replace;
+pub mod report;
+pub mod stream;
+pub mod walk;
+
+pub use args::USAGE;
+pub use color::ColorChoice;
+pub use decode::BinaryFiles;
+pub use error::{MinigrepError, Summary};
+pub use fold::{CaseFolder, Folding, Locale};
+pub use matcher::{Matcher, Span};
+pub use report::OutputMode;
+pub use stream::{Record, Searcher, Sink};
+
+use args::{Arg, Parser};
+use replace::Replacer;
+use report::{FilePrinter, Printer};
+
+use ignore::Filter;
+use index::Query;
+use walk::WalkOptions;
+
+// The file operand naming standard input, and how its results are labelled.
+pub const STDIN: &str = "-";
+pub const STDIN_LABEL: &str = "(standard input)";
+
+#[derive(Default)]
+pub struct Config {
+	pub patterns: Vec<String>,
+	pub pattern_files: Vec<String>,
+	pub filenames: Vec<String>,
+	pub case_sensitive: bool,
+	pub regex: bool,
+	pub max_depth: Option<usize>,
+	pub follow_links: bool,
+	pub hidden: bool,
+	pub no_ignore: bool,
+	// `--glob` and `--iglob` patterns, with whether they ignore case.
+	pub globs: Vec<(String, bool)>,
+	// `--type` and `--type-not` names, with whether they exclude.
+	pub types: Vec<(String, bool)>,
+	pub line_number: bool,
+	pub byte_offset: bool,
+	pub column: bool,
+	pub before_context: usize,
+	pub after_context: usize,
+	pub invert_match: bool,
+	pub word_regexp: bool,
+	pub line_regexp: bool,
+	pub fuzzy: Option<usize>,
+	pub output_mode: OutputMode,
+	pub encoding: Option<&'static Encoding>,
+	pub binary_files: BinaryFiles,
+	pub search_zip: bool,
+	pub case_folding: Folding,
+	pub locale: Locale,
+	pub color: ColorChoice,
+	pub help: bool,
+	pub version: bool,
+	pub jobs: Option<usize>,
+	pub files0_from: Option<String>,
+	pub index: bool,
+	pub replace: Option<String>,
+	pub in_place: Option<String>,
+	pub dry_run: bool,
+}
+
+impl Config {
+	pub fn new(args: &[String]) -> Result<Config, MinigrepError> {
+		let mut config = Config {
+			case_sensitive: env::var("GREP_CASE_INSENSITIVE").is_err(),
+			..Default::default()
+		};
+		let mut positional = Vec::new();
+		
+		let mut parser = Parser::new(args.get(1..).unwrap_or(&[]));
+		while let Some(arg) = parser.next_arg()? {
+			match arg {
+				Arg::Positional(value) => positional.push(value),
+				Arg::Short(c) => config.short_option(c, &mut parser)?,
+				Arg::Long(name) => config.long_option(&name, &mut parser)?,
+			}
+		}
+		
+		if config.help || config.version {
+			return Ok(config);
+		}
+		let pattern_given = !config.patterns.is_empty() || !config.pattern_files.is_empty();
+		if positional.is_empty() && !pattern_given {
+			return Err(MinigrepError::Usage(String::from("Too few arguments (see --help)")));
+		}
+		
+		if config.patterns.is_empty() && config.pattern_files.is_empty() {
+			config.patterns.push(positional.remove(0));
+		}
+		config.filenames = positional;
+		
+		if config.replace.is_none() && (config.in_place.is_some() || config.dry_run) {
+			return Err(MinigrepError::Usage(String::from("--in-place and --dry-run need --replace")));
+		}
+		
+		Ok(config)
+	}
+	
+	fn short_option(&mut self, c: char, parser: &mut Parser) -> Result<(), String> {
+		let name = match c {
+			'E' => "regex",
+			'e' => "regexp",
+			'f' => "file",
+			'i' => "ignore-case",
+			's' => "case-sensitive",
+			'v' => "invert-match",
+			'w' => "word-regexp",
+			'x' => "line-regexp",
+			'n' => "line-number",
+			'b' => "byte-offset",
+			'c' => "count",
+			'l' => "files-with-matches",
+			'L' => "files-without-match",
+			'A' => "after-context",
+			'B' => "before-context",
+			'C' => "context",
+			'g' => "glob",
+			't' => "type",
+			'T' => "type-not",
+			'z' => "search-zip",
+			'a' => "text",
+			'I' => "ignore-binary",
+			'j' => "threads",
+			'h' => "help",
+			'V' => "version",
+			_ => return Err(parser.unknown()),
+		};
+		self.long_option(name, parser)
+	}
+	
+	fn long_option(&mut self, name: &str, parser: &mut Parser) -> Result<(), String> {
+		match name {
+			"regex" => self.regex = true,
+			"regexp" => self.patterns.push(parser.value()?),
+			"file" => self.pattern_files.push(parser.value()?),
+			"ignore-case" => self.case_sensitive = false,
+			"case-sensitive" => self.case_sensitive = true,
+			"case-folding" => {
+				let value = parser.value()?;
+				self.case_folding = match value.as_str() {
+					"simple" => Folding::Simple,
+					"full" => Folding::Full,
+					_ => return Err(parser.invalid(&value)),
+				};
+			}
+			"locale" => {
+				let tag = parser.value()?;
+				self.locale = Locale::from_tag(&tag).ok_or_else(|| parser.invalid(&tag))?;
+			}
+			"invert-match" => self.invert_match = true,
+			"word-regexp" => self.word_regexp = true,
+			"line-regexp" => self.line_regexp = true,
+			"fuzzy" => self.fuzzy = Some(parser.parse_value()?),
+			"line-number" => self.line_number = true,
+			"byte-offset" => self.byte_offset = true,
+			"column" => {
+				self.column = true;
+				self.line_number = true;
+			}
+			"count" => self.output_mode = OutputMode::Count,
+			"files-with-matches" => self.output_mode = OutputMode::FilesWithMatches,
+			"files-without-match" => self.output_mode = OutputMode::FilesWithoutMatch,
+			"json" => self.output_mode = OutputMode::Json,
+			"after-context" => self.after_context = parser.parse_value()?,
+			"before-context" => self.before_context = parser.parse_value()?,
+			"context" => {
+				self.after_context = parser.parse_value()?;
+				self.before_context = self.after_context;
+			}
+			"color" | "colour" => {
+				self.color = match parser.optional_value() {
+					Some(when) => ColorChoice::from_name(&when).ok_or_else(|| parser.invalid(&when))?,
+					None => ColorChoice::Auto,
+				};
+			}
+			"encoding" => {
+				let label = parser.value()?;
+				let encoding = Encoding::for_label(label.as_bytes()).ok_or_else(|| parser.invalid(&label))?;
+				self.encoding = Some(encoding);
+			}
+			"binary-files" => {
+				let value = parser.value()?;
+				self.binary_files = BinaryFiles::from_name(&value).ok_or_else(|| parser.invalid(&value))?;
+			}
+			"search-zip" => self.search_zip = true,
+			"text" => self.binary_files = BinaryFiles::Text,
+			"ignore-binary" => self.binary_files = BinaryFiles::WithoutMatch,
+			"max-depth" => self.max_depth = Some(parser.parse_value()?),
+			"follow" => self.follow_links = true,
+			"hidden" => self.hidden = true,
+			"no-ignore" => self.no_ignore = true,
+			"glob" => self.globs.push((parser.value()?, false)),
+			"iglob" => self.globs.push((parser.value()?, true)),
+			option_name @ ("type" | "type-not") => {
+				let name = parser.value()?;
+				if ignore::file_type(&name).is_none() {
+					return Err(parser.invalid(&name));
+				}
+				self.types.push((name, option_name == "type-not"));
+			}
+			"index" => self.index = true,
+			"files0-from" => self.files0_from = Some(parser.value()?),
+			"threads" => {
+				let jobs: usize = parser.parse_value()?;
+				self.jobs = if jobs == 0 { None } else { Some(jobs) };
+			}
+			"replace" => self.replace = Some(parser.value()?),
+			"in-place" => self.in_place = Some(parser.optional_value().unwrap_or_default()),
+			"dry-run" => self.dry_run = true,
+			"help" => self.help = true,
+			"version" => self.version = true,
+			_ => return Err(parser.unknown()),
+		}
+		
+		Ok(())
+	}
+}
+
+// A matching line. `byte_offset` is where the line starts in the searched
+// text and `spans` are the matches within the line.
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+	pub line_number: usize,
+	pub byte_offset: usize,
+	pub line: &'a str,
+	pub spans: Vec<Span>,
+}
+
+impl<'a> Match<'a> {
+	// 1-based byte column of the first match, as editors expect.
+	pub fn column(&self) -> usize {
+		self.spans.first().map_or(0, |span| span.start) + 1
+	}
+}
+
+// A line in a context group. Lines selected by an inverted search carry no spans.
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+	Match(Match<'a>),
+	Context(Match<'a>),
+}
+
+pub fn run(mut config: Config) -> Result<Summary, MinigrepError> {
+	let started = Instant::now();
+	let patterns = matcher::load_patterns(&config)?;
+	let matcher = matcher::from_config(&config, &patterns)?;
+	let matcher = matcher.as_ref();
+	let mut summary = Summary::default();
+	let mut errors = Vec::new();
+	let query = config.index.then(|| Query::new(&config, &patterns));
+	let (files, saw_dir) = collect_files(&config, query.as_ref(), &mut errors);
+	// Directories often hold rotated, compressed logs.
+	config.search_zip |= saw_dir;
+	let replacer = config.replace.as_ref().map(|template| Replacer::new(template, matcher));
+	let printer = Printer::new(&config, &patterns, saw_dir || files.len() > 1, replacer.as_ref());
+	
+	for e in errors {
+		report(&mut summary, e);
+	}
+	
+	if let Some(replacer) = &replacer {
+		if config.in_place.is_some() || config.dry_run {
+			let mut stdout = io::stdout().lock();
+			return match replace::edit_files(&config, matcher, replacer, &files, &mut summary, &mut stdout) {
+				Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(MinigrepError::io("(standard output)", e)),
+				_ => Ok(summary),
+			};
+		}
+	}
+	
+	let jobs = match config.jobs {
+		Some(jobs) => jobs,
+		None => thread::available_parallelism().map_or(1, |n| n.get()),
+	};
+	let mut stdout = io::stdout().lock();
+	let result = if jobs > 1 && files.len() > 1 {
+		search_parallel(&config, matcher, &printer, &files, jobs, &mut summary, &mut stdout)
+	} else {
+		search_sequential(&config, matcher, &printer, &files, &mut summary, &mut stdout)
+	};
+	
+	match result {
+		Ok(()) => {}
+		// The reader went away, e.g. `minigrep ... | head`.
+		Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(summary),
+		Err(e) => return Err(MinigrepError::io("(standard output)", e)),
+	}
+	
+	match printer.summary(&mut stdout, &summary, started.elapsed()) {
+		Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(MinigrepError::io("(standard output)", e)),
+		_ => Ok(summary),
+	}
+}
+
+// Searches one file, writing its output to `out`. Returns the number of
+// selected lines along with the writer and whether a group was printed.
+fn search_file<W: Write>(
+	config: &Config,
+	matcher: &dyn Matcher,
+	printer: &Printer,
+	file: &Path,
+	out: W,
+	separate: bool,
+) -> (Result<usize, MinigrepError>, io::Result<(W, bool)>) {
+	let (reader, label) = if file == Path::new(STDIN) {
+		(decode::wrap(io::stdin().lock(), config.encoding, config.search_zip), Path::new(STDIN_LABEL))
+	} else {
+		(decode::open(file, config.encoding, config.search_zip), file)
+	};
+	let mut file_printer = printer.file(label, out, separate);
+	
+	let count = match reader {
+		Ok(reader) => search_contents(config, matcher, reader, &mut file_printer),
+		Err(e) => Err(e),
+	};
+	let count = count.map_err(|e| MinigrepError::io(label, e));
+	
+	(count, file_printer.finish())
+}
+
+// Searches an opened file. Files that look binary are searched only to
+// count their matches, unless they are to be treated as text.
+fn search_contents<W: Write>(
+	config: &Config,
+	matcher: &dyn Matcher,
+	mut reader: Box<dyn BufRead>,
+	printer: &mut FilePrinter<W>,
+) -> io::Result<usize> {
+	let text = config.binary_files == BinaryFiles::Text;
+	let binary = !text && decode::is_binary(reader.fill_buf()?);
+	printer.begin();
+	
+	let count = if !binary {
+		Searcher::new()
+			.invert(config.invert_match)
+			.before_context(config.before_context)
+			.after_context(config.after_context)
+			.lossy(text)
+			.search(matcher, reader, printer)?
+	} else if config.binary_files == BinaryFiles::WithoutMatch {
+		0
+	} else {
+		let count = Searcher::new().invert(config.invert_match).lossy(true).count(matcher, reader)?;
+		if count > 0 {
+			printer.binary_matches();
+		}
+		count
+	};
+	
+	printer.end(count);
+	Ok(count)
+}
+
+fn search_sequential<W: Write>(
+	config: &Config,
+	matcher: &dyn Matcher,
+	printer: &Printer,
+	files: &[PathBuf],
+	summary: &mut Summary,
+	out: &mut W,
+) -> io::Result<()> {
+	let mut printed_group = false;
+	
+	for file in files {
+		let (count, output) = search_file(config, matcher, printer, file, &mut *out, printed_group);
+		let (_, printed) = output?;
+		printed_group = printed;
+		record(summary, count);
+	}
+	
+	Ok(())
+}
+
+// Searches files on `jobs` worker threads. Each file's output is collected
+// in memory and printed once every file before it has been printed, so the
+// output is the same as a sequential search.
+fn search_parallel<W: Write>(
+	config: &Config,
+	matcher: &dyn Matcher,
+	printer: &Printer,
+	files: &[PathBuf],
+	jobs: usize,
+	summary: &mut Summary,
+	out: &mut W,
+) -> io::Result<()> {
+	let next = AtomicUsize::new(0);
+	let (sender, receiver) = mpsc::channel();
+	
+	thread::scope(|scope| {
+		for _ in 0..jobs.min(files.len()) {
+			let sender = sender.clone();
+			let next = &next;
+			scope.spawn(move || loop {
+				let i = next.fetch_add(1, Ordering::Relaxed);
+				if i >= files.len() {
+					break;
+				}
+				let result = search_file(config, matcher, printer, &files[i], Vec::new(), false);
+				if sender.send((i, result)).is_err() {
+					break;
+				}
+			});
+		}
+		drop(sender);
+		
+		let mut pending = BTreeMap::new();
+		let mut next_to_print = 0;
+		let mut printed_group = false;
+		
+		for (i, result) in receiver {
+			pending.insert(i, result);
+			
+			while let Some((count, output)) = pending.remove(&next_to_print) {
+				// Writing into a Vec can't fail.
+				let (buffer, printed) = output.unwrap();
+				if printed && printed_group {
+					if let Some(separator) = printer.separator() {
+						writeln!(out, "{}", separator)?;
+					}
+				}
+				printed_group |= printed;
+				out.write_all(&buffer)?;
+				record(summary, count);
+				next_to_print += 1;
+			}
+		}
+		
+		Ok(())
+	})
+}
+
+fn record(summary: &mut Summary, count: Result<usize, MinigrepError>) {
+	match count {
+		Ok(count) => {
+			summary.files_searched += 1;
+			summary.matched_lines += count;
+			if count > 0 {
+				summary.files_matched += 1;
+			}
+		}
+		Err(e) => report(summary, e),
+	}
+}
+
+fn report(summary: &mut Summary, e: MinigrepError) {
+	eprintln!("minigrep: {}", e);
+	summary.errors += 1;
+}
+
+// Expands the file operands into the list of files to search. Operands that
+// can't be expanded are added to `errors` and skipped. Also says whether a
+// directory was given, since results from a walk are always shown with their
+// path. With a `query`, directories are narrowed down with their indexes.
+fn collect_files(
+	config: &Config,
+	query: Option<&Query>,
+	errors: &mut Vec<MinigrepError>,
+) -> (Vec<PathBuf>, bool) {
+	let mut options = WalkOptions {
+		max_depth: config.max_depth,
+		follow_links: config.follow_links,
+		hidden: config.hidden,
+		ignore_files: !config.no_ignore,
+		filter: Filter::default(),
+	};
+	for (pattern, ignore_case) in &config.globs {
+		options.filter.add_glob(pattern, *ignore_case);
+	}
+	for (name, negated) in &config.types {
+		options.filter.add_type(name, *negated);
+	}
+	let walk_dir = |dir: &Path, errors: &mut Vec<MinigrepError>| {
+		let files = walk::walk(dir, &options, errors);
+		match query {
+			Some(query) => index::filter(dir, files, query, errors),
+			None => files,
+		}
+	};
+	let mut files = Vec::new();
+	let mut saw_dir = false;
+	
+	if config.filenames.is_empty() && config.files0_from.is_none() {
+		files.push(PathBuf::from(STDIN));
+	}
+	
+	for name in &config.filenames {
+		let path = Path::new(name);
+		
+		if name == STDIN {
+			files.push(path.to_path_buf());
+		} else if !path.exists() && glob::is_glob(name) {
+			let matched = glob::expand(name, errors);
+			if matched.is_empty() {
+				let e = io::Error::new(io::ErrorKind::NotFound, "no files match this pattern");
+				errors.push(MinigrepError::io(name, e));
+			}
+			files.extend(matched);
+		} else if path.is_dir() {
+			saw_dir = true;
+			files.extend(walk_dir(path, errors));
+		} else {
+			files.push(path.to_path_buf());
+		}
+	}
+	
+	if let Some(list) = &config.files0_from {
+		match read_files0(list) {
+			Ok(names) => {
+				for path in names {
+					if path.is_dir() {
+						saw_dir = true;
+						files.extend(walk_dir(&path, errors));
+					} else {
+						files.push(path);
+					}
+				}
+			}
+			Err(e) => errors.push(MinigrepError::io(list, e)),
+		}
+	}
+	
+	(files, saw_dir)
+}
+
+// Reads a list of NUL-separated file names, from stdin if `list` is `-`.
+fn read_files0(list: &str) -> io::Result<Vec<PathBuf>> {
+	let mut bytes = Vec::new();
+	if list == STDIN {
+		io::stdin().lock().read_to_end(&mut bytes)?;
+	} else {
+		bytes = fs::read(list)?;
+	}
+	
+	Ok(bytes
+		.split(|&b| b == 0)
+		.filter(|name| !name.is_empty())
+		.map(path_from_bytes)
+		.collect())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+	use std::ffi::OsStr;
+	use std::os::unix::ffi::OsStrExt;
+	
+	PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+	PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	let mut result = Vec::new();
+	
+	for line in contents.lines() {
+		if line.contains(query) {
+			result.push(line);
+		}
+	}
+	
+	result
+}
+
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	let folder = CaseFolder::default();
+	let query = folder.fold_str(query);
+	let mut result = Vec::new();
+	
+	for line in contents.lines() {
+		if folder.find(&query, line, 0).is_some() {
+			result.push(line);
+		}
+	}
+	
+	result
+}
+
+pub fn search_regex<'a>(re: &Regex, contents: &'a str) -> Vec<&'a str> {
+	let mut result = Vec::new();
+	
+	for line in contents.lines() {
+		if re.is_match(line) {
+			result.push(line);
+		}
+	}
+	
+	result
+}
+
+pub fn search_with<'a>(matcher: &dyn Matcher, contents: &'a str) -> Vec<&'a str> {
+	let mut result = Vec::new();
+	
+	for line in contents.lines() {
+		if matcher.is_match(line) {
+			result.push(line);
+		}
+	}
+	
+	result
+}
+
+pub fn find_matches<'a>(matcher: &dyn Matcher, contents: &'a str) -> Vec<Match<'a>> {
+	let mut result = Vec::new();
+	
+	for (i, (byte_offset, line)) in lines_with_offsets(contents).enumerate() {
+		let spans = matcher.find_iter(line);
+		if !spans.is_empty() {
+			result.push(Match { line_number: i + 1, byte_offset, line, spans });
+		}
+	}
+	
+	result
+}
+
+// Groups selected lines together with up to `before` lines of context ahead
+// of them and `after` lines following them. Overlapping or adjacent windows
+// are merged into a single group. With `invert`, the lines that do not match
+// are the selected ones.
+pub fn find_with_context<'a>(
+	matcher: &dyn Matcher,
+	contents: &'a str,
+	invert: bool,
+	before: usize,
+	after: usize,
+) -> Vec<Vec<Line<'a>>> {
+	let mut groups = Groups { contents, groups: Vec::new() };
+	let result = Searcher::new()
+		.invert(invert)
+		.before_context(before)
+		.after_context(after)
+		.search(matcher, contents.as_bytes(), &mut groups);
+	// A `&str` is always valid UTF-8 and reading from a slice can't fail.
+	result.unwrap();
+	
+	groups.groups
+}
+
+// The lines handed to a sink only live for the call, so they are
+// re-borrowed from `contents` using their offsets.
+struct Groups<'a> {
+	contents: &'a str,
+	groups: Vec<Vec<Line<'a>>>,
+}
+
+impl<'a> Sink for Groups<'a> {
+	fn group_start(&mut self) {
+		self.groups.push(Vec::new());
+	}
+	
+	fn line(&mut self, line: &Line) -> bool {
+		let (m, is_match) = match line {
+			Line::Match(m) => (m, true),
+			Line::Context(m) => (m, false),
+		};
+		let start = m.byte_offset;
+		let m = Match {
+			line_number: m.line_number,
+			byte_offset: start,
+			line: &self.contents[start..start + m.line.len()],
+			spans: m.spans.clone(),
+		};
+		let line = if is_match { Line::Match(m) } else { Line::Context(m) };
+		self.groups.last_mut().unwrap().push(line);
+		true
+	}
+}
+
+// Same lines as `str::lines`, paired with the offset each one starts at.
+fn lines_with_offsets(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+	let mut offset = 0;
+	
+	contents.split_inclusive('\n').map(move |raw| {
+		let start = offset;
+		offset += raw.len();
+		
+		let line = raw.strip_suffix('\n').unwrap_or(raw);
+		let line = line.strip_suffix('\r').unwrap_or(line);
+		(start, line)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn on_result() {
+		let query = "duct";
+		let contents = "\
+Rust:
+safe, fast, productive.
+pick three.";
+		
+		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+	}
+	
+	#[test]
+	fn case_sensitive() {
+		let query = "duct";
+		let contents = "\
+Rust:
+safe, fast, productive.
+pick three.
+Duct type.";
+		
+		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+	}
+	
+	#[test]
+	fn case_insensitive() {
+		let query = "rUsT";
+		let contents = "\
+Rust:
+safe, fast, productive.
+pick three.
+Trust me.";
+		
+		assert_eq!(vec!["Rust:", "Trust me."], search_case_insensitive(query, contents));
+	}
+	
+	#[test]
+	fn regex_query() {
+		let re = Regex::new(r"^(safe|pick)\b.*[.]$").unwrap();
+		let contents = "\
+Rust:
+safe, fast, productive.
+pick three.
+unsafe code.";
+		
+		assert_eq!(vec!["safe, fast, productive.", "pick three."], search_regex(&re, contents));
+	}
+	
+	#[test]
+	fn regex_case_insensitive() {
+		let config = Config {
+			case_sensitive: false,
+			regex: true,
+			..Default::default()
+		};
+		let matcher = matcher::from_config(&config, &[String::from("^r[a-z]+:$")]).unwrap();
+		let contents = "\
+Rust:
+Trust:
+rUST:";
+		
+		assert_eq!(vec!["Rust:", "rUST:"], search_with(matcher.as_ref(), contents));
+	}
+	
+	#[test]
+	fn match_positions() {
+		let matcher = matcher::Literal::new("fast");
+		let contents = "Rust:\r\nsafe, fast, productive.\npick three.";
+		
+		assert_eq!(
+			vec![Match {
+				line_number: 2,
+				byte_offset: 7,
+				line: "safe, fast, productive.",
+				spans: vec![Span::new(6, 10)],
+			}],
+			find_matches(&matcher, contents)
+		);
+	}
+	
+	#[test]
+	fn context_groups() {
+		let matcher = matcher::Literal::new("x");
+		let contents = "1\nx2\n3\n4\n5\n6\nx7\n8";
+		let groups = find_with_context(&matcher, contents, false, 1, 1);
+		
+		let numbers: Vec<Vec<(usize, bool)>> = groups
+			.iter()
+			.map(|group| {
+				group
+					.iter()
+					.map(|line| match line {
+						Line::Match(m) => (m.line_number, true),
+						Line::Context(m) => (m.line_number, false),
+					})
+					.collect()
+			})
+			.collect();
+		assert_eq!(
+			vec![
+				vec![(1, false), (2, true), (3, false)],
+				vec![(6, false), (7, true), (8, false)],
+			],
+			numbers
+		);
+	}
+	
+	#[test]
+	fn overlapping_context_merges() {
+		let matcher = matcher::Literal::new("x");
+		let contents = "x1\n2\n3\nx4";
+		
+		assert_eq!(1, find_with_context(&matcher, contents, false, 1, 1).len());
+		assert_eq!(2, find_with_context(&matcher, contents, false, 0, 1).len());
+	}
+	
+	#[test]
+	fn inverted_search() {
+		let matcher = matcher::Literal::new("duct");
+		let contents = "\
+Rust:
+safe, fast, productive.
+pick three.";
+		let groups = find_with_context(&matcher, contents, true, 0, 0);
+		
+		let lines: Vec<&str> = groups
+			.iter()
+			.flatten()
+			.map(|line| match line {
+				Line::Match(m) | Line::Context(m) => m.line,
+			})
+			.collect();
+		assert_eq!(vec!["Rust:", "pick three."], lines);
+	}
+	
+	#[test]
+	fn flags_override_environment() {
+		let args: Vec<String> = ["minigrep", "-in", "--", "-pattern", "poem.txt"]
+			.iter()
+			.map(|arg| arg.to_string())
+			.collect();
+		let config = Config::new(&args).unwrap();
+		
+		assert!(!config.case_sensitive);
+		assert!(config.line_number);
+		assert_eq!(vec!["-pattern"], config.patterns);
+		assert_eq!(vec!["poem.txt"], config.filenames);
+	}
+	
+	#[test]
+	fn explicit_patterns() {
+		let args: Vec<String> = ["minigrep", "-e", "rust", "-enobody", "poem.txt", "lib.rs"]
+			.iter()
+			.map(|arg| arg.to_string())
+			.collect();
+		let config = Config::new(&args).unwrap();
+		
+		assert_eq!(vec!["rust", "nobody"], config.patterns);
+		assert_eq!(vec!["poem.txt", "lib.rs"], config.filenames);
+	}
+	
+	#[test]
+	fn unknown_option() {
+		let args: Vec<String> = ["minigrep", "--frobnicate", "x", "poem.txt"]
+			.iter()
+			.map(|arg| arg.to_string())
+			.collect();
+		
+		let error = Config::new(&args).err().unwrap();
+		assert!(matches!(error, MinigrepError::Usage(_)));
+		assert_eq!("unknown option '--frobnicate' (see --help)", error.to_string());
+	}
+	
+	#[test]
+	fn parallel_output_in_file_order() {
+		let dir = env::temp_dir().join("minigrep-parallel-tests");
+		fs::create_dir_all(&dir).unwrap();
+		let files: Vec<PathBuf> = (0..6)
+			.map(|i| {
+				let file = dir.join(format!("{}.txt", i));
+				let contents = if i == 3 { String::from("nothing\n") } else { format!("a{0}\nx{0}\nb{0}\n", i) };
+				fs::write(&file, contents).unwrap();
+				file
+			})
+			.collect();
+		let config = Config { case_sensitive: true, before_context: 1, after_context: 1, ..Default::default() };
+		let patterns = vec![String::from("x")];
+		let matcher = matcher::from_config(&config, &patterns).unwrap();
+		let printer = Printer::new(&config, &patterns, true, None);
+		
+		let mut expected = Vec::new();
+		for (i, file) in files.iter().enumerate().filter(|(i, _)| *i != 3) {
+			let name = file.display();
+			let separator = if i > 0 { "--\n" } else { "" };
+			expected.push(format!("{1}{0}-a{2}\n{0}:x{2}\n{0}-b{2}\n", name, separator, i));
+		}
+		let expected = expected.concat();
+		
+		let mut summary = Summary::default();
+		let mut out = Vec::new();
+		search_parallel(&config, matcher.as_ref(), &printer, &files, 3, &mut summary, &mut out).unwrap();
+		assert_eq!(expected, String::from_utf8(out).unwrap());
+		assert_eq!((6, 5, 5), (summary.files_searched, summary.files_matched, summary.matched_lines));
+		
+		let mut out = Vec::new();
+		search_sequential(&config, matcher.as_ref(), &printer, &files, &mut Summary::default(), &mut out).unwrap();
+		assert_eq!(expected, String::from_utf8(out).unwrap());
+	}
+	
+	#[test]
+	fn pattern_only_reads_stdin() {
+		let args: Vec<String> = ["minigrep", "nobody"].iter().map(|arg| arg.to_string()).collect();
+		let config = Config::new(&args).unwrap();
+		let (files, _) = collect_files(&config, None, &mut Vec::new());
+		
+		assert_eq!(vec![PathBuf::from(STDIN)], files);
+	}
+}
@@ -4,16 +4,38 @@ use std::process;
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+	if let Some(dir) = minigrep::index::build_command(&args) {
+		match minigrep::index::command(dir) {
+			Ok(code) => process::exit(code),
+			Err(e) => {
+				eprintln!("Application error: {}", e);
+				process::exit(2);
+			}
+		}
+	}
+
     let config = minigrep::Config::new(&args).unwrap_or_else(|error| {
 		eprintln!("Problem parsing arguments: {}", error);
-		process::exit(1);
+		process::exit(2);
 	});
 
-    //println!("query string: {}", config.query);
+	if config.help {
+		print!("{}", minigrep::USAGE);
+		return;
+	}
+	if config.version {
+		println!("minigrep {}", env!("CARGO_PKG_VERSION"));
+		return;
+	}
+
+    //println!("query string: {:?}", config.patterns);
     //println!("search file: {}", config.filename);
 	
-	if let Err(e) = minigrep::run(config) {
-		eprintln!("Application error: {}", e);
-		process::exit(1);
+	match minigrep::run(config) {
+		Ok(summary) => process::exit(summary.exit_code()),
+		Err(e) => {
+			eprintln!("Application error: {}", e);
+			process::exit(2);
+		}
 	}
 }
@@ -0,0 +1,710 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Read};
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use memchr::memmem::Finder;
+use regex::{Regex, RegexBuilder};
+use regex_automata::meta;
+use regex_automata::util::syntax;
+
+use crate::fold::CaseFolder;
+use crate::fuzzy::Fuzzy;
+use crate::{Config, MinigrepError, STDIN};
+
+// A match within a line: its byte range, the index of the pattern that
+// produced it and, for fuzzy matches, how many edits it is away from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	pub pattern: usize,
+	pub distance: usize,
+}
+
+impl Span {
+	pub fn new(start: usize, end: usize) -> Span {
+		Span { start, end, pattern: 0, distance: 0 }
+	}
+	
+	pub fn for_pattern(start: usize, end: usize, pattern: usize) -> Span {
+		Span { start, end, pattern, distance: 0 }
+	}
+	
+	pub fn fuzzy(start: usize, end: usize, distance: usize) -> Span {
+		Span { start, end, pattern: 0, distance }
+	}
+}
+
+// Finds matches within a line. Implemented by each kind of pattern minigrep
+// supports, and open to other implementations for use with a `Searcher`.
+pub trait Matcher: Send + Sync {
+	// The first match starting at or after byte `from`, which must be on a
+	// character boundary.
+	fn find_at(&self, line: &str, from: usize) -> Option<Span>;
+	
+	fn is_match(&self, line: &str) -> bool {
+		self.find_at(line, 0).is_some()
+	}
+	
+	// Every non-overlapping match in `line`.
+	fn find_iter(&self, line: &str) -> Vec<Span> {
+		let mut spans = Vec::new();
+		let mut from = 0;
+		
+		while let Some(span) = self.find_at(line, from) {
+			spans.push(span);
+			from = if span.end > span.start {
+				span.end
+			} else {
+				match next_char(line, span.end) {
+					Some(next) => next,
+					None => break,
+				}
+			};
+		}
+		
+		spans
+	}
+	
+	// The regular expression behind a single-pattern regex search, for
+	// access to its capture groups.
+	fn regex(&self) -> Option<&Regex> {
+		None
+	}
+	
+	// The same for a search for several regular expressions. Spans name the
+	// pattern they matched.
+	fn regex_set(&self) -> Option<&meta::Regex> {
+		None
+	}
+	
+	// A string every matching line contains, for matchers that have one.
+	// Lets a `Searcher` skim whole buffers for it instead of going line by
+	// line.
+	fn required_literal(&self) -> Option<&str> {
+		None
+	}
+	
+	// Whether the matches are exactly the occurrences of `required_literal`,
+	// so that a `Searcher` which found one needn't ask the matcher again.
+	fn matches_literal(&self) -> bool {
+		false
+	}
+}
+
+// Collects the patterns given with `-e` and positionally together with the
+// ones read from `-f` files, one per line.
+pub fn load_patterns(config: &Config) -> Result<Vec<String>, MinigrepError> {
+	let mut patterns = config.patterns.clone();
+	
+	for file in &config.pattern_files {
+		let mut contents = String::new();
+		let result = if file == STDIN {
+			io::stdin().lock().read_to_string(&mut contents).map(|_| ())
+		} else {
+			fs::read_to_string(file).map(|text| contents = text)
+		};
+		result.map_err(|e| MinigrepError::io(file, e))?;
+		
+		patterns.extend(contents.lines().map(|line| line.to_string()));
+	}
+	
+	Ok(patterns)
+}
+
+// Builds the matcher for the patterns and matching options in `config`.
+pub fn from_config(config: &Config, patterns: &[String]) -> Result<Box<dyn Matcher>, MinigrepError> {
+	// A regex is anchored instead, so that alternatives preferring a
+	// shorter match can still match the whole line.
+	if config.line_regexp && config.regex {
+		let anchored: Vec<String> = patterns.iter().map(|p| format!("^(?:{})$", p)).collect();
+		return build(config, &anchored);
+	}
+	// Likewise bounded, so that alternatives can give way to one ending at
+	// a word boundary.
+	if config.word_regexp && config.regex {
+		let bounded: Vec<String> = patterns.iter().map(|p| format!(r"\b{{start-half}}(?:{})\b{{end-half}}", p)).collect();
+		let bounded = build(config, &bounded)?;
+		let plain = Word(build(config, patterns)?);
+		return Ok(Box::new(WordRegex { bounded, plain }));
+	}
+	
+	let matcher = build(config, patterns)?;
+	if config.line_regexp {
+		Ok(Box::new(WholeLine(matcher)))
+	} else if config.word_regexp && !is_literal_set(config, patterns) {
+		Ok(Box::new(Word(matcher)))
+	} else {
+		Ok(matcher)
+	}
+}
+
+// Whether the patterns are searched for with `Literals`, which finds whole
+// words itself.
+fn is_literal_set(config: &Config, patterns: &[String]) -> bool {
+	!config.regex && config.fuzzy.is_none() && patterns.len() > 1
+}
+
+fn build(config: &Config, patterns: &[String]) -> Result<Box<dyn Matcher>, MinigrepError> {
+	if let Some(max_distance) = config.fuzzy {
+		if config.regex {
+			return Err(MinigrepError::Usage(String::from("--fuzzy can't be combined with --regex")));
+		}
+		let folder = if config.case_sensitive {
+			None
+		} else {
+			Some(CaseFolder::new(config.case_folding, config.locale))
+		};
+		let fuzzy = patterns.iter().map(|p| Fuzzy::new(p, max_distance, folder)).collect();
+		return Ok(Box::new(FuzzyPatterns(fuzzy)));
+	}
+	
+	if let [query] = patterns {
+		return single(config, query);
+	}
+	
+	if is_literal_set(config, patterns) {
+		let folder = if config.case_sensitive {
+			None
+		} else {
+			Some(CaseFolder::new(config.case_folding, config.locale))
+		};
+		let words = config.word_regexp && !config.line_regexp;
+		return Ok(Box::new(Literals::new(patterns, folder, words)?));
+	}
+	
+	let re = meta::Builder::new()
+		.syntax(syntax::Config::new().case_insensitive(!config.case_sensitive))
+		.build_many(patterns)
+		.map_err(|e| MinigrepError::Pattern(e.to_string()))?;
+	Ok(Box::new(Patterns(re)))
+}
+
+fn single(config: &Config, query: &str) -> Result<Box<dyn Matcher>, MinigrepError> {
+	if config.regex {
+		let re = RegexBuilder::new(query)
+			.case_insensitive(!config.case_sensitive)
+			.build()?;
+		Ok(Box::new(RegexMatcher(re)))
+	} else if config.case_sensitive {
+		Ok(Box::new(Literal::new(query)))
+	} else {
+		let folder = CaseFolder::new(config.case_folding, config.locale);
+		Ok(Box::new(CaseInsensitive::new(query, folder)))
+	}
+}
+
+pub struct Literal {
+	query: String,
+	finder: Finder<'static>,
+}
+
+impl Literal {
+	pub fn new(query: &str) -> Literal {
+		Literal { query: query.to_string(), finder: Finder::new(query).into_owned() }
+	}
+}
+
+impl Matcher for Literal {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		let start = from + self.finder.find(&line.as_bytes()[from..])?;
+		Some(Span::new(start, start + self.query.len()))
+	}
+	
+	fn required_literal(&self) -> Option<&str> {
+		Some(&self.query)
+	}
+	
+	fn matches_literal(&self) -> bool {
+		true
+	}
+}
+
+// The query is stored already case folded.
+pub struct CaseInsensitive {
+	query: Vec<char>,
+	folder: CaseFolder,
+}
+
+impl CaseInsensitive {
+	pub fn new(query: &str, folder: CaseFolder) -> CaseInsensitive {
+		CaseInsensitive { query: folder.fold_str(query), folder }
+	}
+}
+
+impl Matcher for CaseInsensitive {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		self.folder.find(&self.query, line, from).map(|(start, end)| Span::new(start, end))
+	}
+}
+
+pub struct RegexMatcher(pub Regex);
+
+impl Matcher for RegexMatcher {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		self.0.find_at(line, from).map(|m| Span::new(m.start(), m.end()))
+	}
+	
+	fn is_match(&self, line: &str) -> bool {
+		self.0.is_match(line)
+	}
+	
+	fn regex(&self) -> Option<&Regex> {
+		Some(&self.0)
+	}
+}
+
+// Several literal patterns, searched for together. Of matches starting at
+// the same place, the longest wins. With a folder, the patterns are stored
+// folded and each line is folded before it is searched, so a set of
+// patterns matches just what each of them would on its own. With `words`,
+// only matches that start and end at word boundaries count.
+pub struct Literals {
+	ac: AhoCorasick,
+	folder: Option<CaseFolder>,
+	words: bool,
+}
+
+impl Literals {
+	pub fn new(patterns: &[String], folder: Option<CaseFolder>, words: bool) -> Result<Literals, MinigrepError> {
+		// Matches that are skipped, such as ones in the folded line that don't
+		// start and end between the foldings of whole chars, may hide others,
+		// so then every match is needed and not just the leftmost ones.
+		let kind = if folder.is_some() || words {
+			MatchKind::Standard
+		} else {
+			MatchKind::LeftmostLongest
+		};
+		let ac = match folder {
+			Some(folder) => AhoCorasick::builder()
+				.match_kind(kind)
+				.build(patterns.iter().map(|p| folder.fold_str(p).into_iter().collect::<String>())),
+			None => AhoCorasick::builder().match_kind(kind).build(patterns),
+		};
+		let ac = ac.map_err(|e| MinigrepError::Pattern(e.to_string()))?;
+		Ok(Literals { ac, folder, words })
+	}
+	
+	// Hands each match in `line` from `from` on to `each`, in the order they
+	// end in the line as searched, until it returns false. Matches that
+	// don't count come with no span. The line is folded only once.
+	fn overlapping<F>(&self, line: &str, from: usize, mut each: F)
+	where
+		F: FnMut(&aho_corasick::Match, Option<Span>) -> bool,
+	{
+		let (haystack, offsets) = match &self.folder {
+			Some(folder) => {
+				let (folded, offsets) = folder.fold_with_offsets(&line[from..]);
+				(Cow::Owned(folded), Some(offsets))
+			}
+			None => (Cow::Borrowed(&line[from..]), None),
+		};
+		let original = |at| match &offsets {
+			Some(offsets) => {
+				let i = offsets.binary_search_by_key(&at, |&(folded, _)| folded).ok()?;
+				Some(from + offsets[i].1)
+			}
+			None => Some(from + at),
+		};
+		let accepted = |start, end| !self.words || (is_word_boundary(line, start) && is_word_boundary(line, end));
+		
+		for m in self.ac.find_overlapping_iter(haystack.as_ref()) {
+			let span = match (original(m.start()), original(m.end())) {
+				(Some(start), Some(end)) if accepted(start, end) => {
+					Some(Span::for_pattern(start, end, m.pattern().as_usize()))
+				}
+				_ => None,
+			};
+			if !each(&m, span) {
+				return;
+			}
+		}
+	}
+	
+	fn find_overlapping(&self, line: &str, from: usize) -> Option<Span> {
+		// Overlapping matches come in order of where they end, so once they
+		// end too far along none can start before the best one.
+		let mut best: Option<(usize, Span)> = None;
+		self.overlapping(line, from, |m, span| {
+			if let Some((start, _)) = best {
+				if m.end() > start + self.ac.max_pattern_len() {
+					return false;
+				}
+			}
+			if let Some(span) = span {
+				let better = match best {
+					Some((_, best)) => span.start < best.start || (span.start == best.start && span.end > best.end),
+					None => true,
+				};
+				if better {
+					best = Some((m.start(), span));
+				}
+			}
+			true
+		});
+		
+		best.map(|(_, span)| span)
+	}
+}
+
+impl Matcher for Literals {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		if self.ac.match_kind() == MatchKind::Standard {
+			return self.find_overlapping(line, from);
+		}
+		self.ac
+			.find(aho_corasick::Input::new(line).range(from..))
+			.map(|m| Span::for_pattern(m.start(), m.end(), m.pattern().as_usize()))
+	}
+	
+	fn is_match(&self, line: &str) -> bool {
+		if self.ac.match_kind() == MatchKind::Standard {
+			let mut found = false;
+			self.overlapping(line, 0, |_, span| {
+				found = span.is_some();
+				!found
+			});
+			return found;
+		}
+		self.ac.is_match(line)
+	}
+	
+	// Goes over the line once, taking the leftmost-longest of the matches
+	// that count and then the next one after it, the way `find_at` would.
+	fn find_iter(&self, line: &str) -> Vec<Span> {
+		if self.ac.match_kind() != MatchKind::Standard {
+			let matches = self.ac.find_iter(line);
+			return matches.map(|m| Span::for_pattern(m.start(), m.end(), m.pattern().as_usize())).collect();
+		}
+		
+		let mut found = Vec::new();
+		self.overlapping(line, 0, |_, span| {
+			found.extend(span);
+			true
+		});
+		found.sort_by_key(|span| (span.start, std::cmp::Reverse(span.end)));
+		
+		let mut spans: Vec<Span> = Vec::new();
+		let mut from = 0;
+		for span in found {
+			if span.start < from {
+				continue;
+			}
+			spans.push(span);
+			from = if span.end > span.start {
+				span.end
+			} else {
+				match next_char(line, span.end) {
+					Some(next) => next,
+					None => break,
+				}
+			};
+		}
+		
+		spans
+	}
+}
+
+// Several regular expressions matched by one automaton. Like a single
+// regular expression, they use simple case folding when ignoring case.
+pub struct Patterns(pub meta::Regex);
+
+impl Matcher for Patterns {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		self.0
+			.find(regex_automata::Input::new(line).range(from..))
+			.map(|m| Span::for_pattern(m.start(), m.end(), m.pattern().as_usize()))
+	}
+	
+	fn is_match(&self, line: &str) -> bool {
+		self.0.is_match(line)
+	}
+	
+	fn regex_set(&self) -> Option<&meta::Regex> {
+		Some(&self.0)
+	}
+}
+
+impl Matcher for Fuzzy {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		Fuzzy::find_at(self, line, from)
+	}
+}
+
+// Approximate matches of several patterns. Of matches starting at the same
+// place, the closest one wins.
+pub struct FuzzyPatterns(pub Vec<Fuzzy>);
+
+impl Matcher for FuzzyPatterns {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		let mut best: Option<Span> = None;
+		for (i, fuzzy) in self.0.iter().enumerate() {
+			if let Some(span) = fuzzy.find_at(line, from) {
+				let better = match best {
+					Some(best) => (span.start, span.distance) < (best.start, best.distance),
+					None => true,
+				};
+				if better {
+					best = Some(Span { pattern: i, ..span });
+				}
+			}
+		}
+		best
+	}
+}
+
+// Only matches of the inner matcher that start and end at word boundaries.
+pub struct Word(pub Box<dyn Matcher>);
+
+impl Matcher for Word {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		let mut from = from;
+		loop {
+			let span = self.0.find_at(line, from)?;
+			if is_word_boundary(line, span.start) && is_word_boundary(line, span.end) {
+				return Some(span);
+			}
+			// Try again just past where the rejected match began.
+			from = next_char(line, span.start)?;
+		}
+	}
+	
+	fn regex(&self) -> Option<&Regex> {
+		self.0.regex()
+	}
+	
+	fn regex_set(&self) -> Option<&meta::Regex> {
+		self.0.regex_set()
+	}
+	
+	fn required_literal(&self) -> Option<&str> {
+		self.0.required_literal()
+	}
+}
+
+// Whole words for regular expressions. `bounded` holds the patterns wrapped
+// in the engine's own word boundary assertions. The engine doesn't know that
+// CJK chars are words of their own, so the matches `plain` finds between
+// them are taken as well.
+pub struct WordRegex {
+	bounded: Box<dyn Matcher>,
+	plain: Word,
+}
+
+impl Matcher for WordRegex {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		match (self.bounded.find_at(line, from), self.plain.find_at(line, from)) {
+			(Some(a), Some(b)) if b.start < a.start || (b.start == a.start && b.end > a.end) => Some(b),
+			(Some(a), _) => Some(a),
+			(None, b) => b,
+		}
+	}
+	
+	fn regex(&self) -> Option<&Regex> {
+		self.bounded.regex()
+	}
+	
+	fn regex_set(&self) -> Option<&meta::Regex> {
+		self.bounded.regex_set()
+	}
+}
+
+// Only matches of the inner matcher that cover the whole line.
+pub struct WholeLine(pub Box<dyn Matcher>);
+
+impl Matcher for WholeLine {
+	fn find_at(&self, line: &str, from: usize) -> Option<Span> {
+		if from > 0 {
+			return None;
+		}
+		self.0.find_at(line, 0).filter(|span| span.start == 0 && span.end == line.len())
+	}
+	
+	fn regex(&self) -> Option<&Regex> {
+		self.0.regex()
+	}
+	
+	fn regex_set(&self) -> Option<&meta::Regex> {
+		self.0.regex_set()
+	}
+	
+	fn required_literal(&self) -> Option<&str> {
+		self.0.required_literal()
+	}
+}
+
+fn next_char(line: &str, at: usize) -> Option<usize> {
+	line[at..].chars().next().map(|c| at + c.len_utf8())
+}
+
+// The same word chars as `\w` in a regex, so that its word boundaries agree.
+fn is_word_char(c: char) -> bool {
+	regex_syntax::is_word_character(c)
+}
+
+// Han ideographs and hiragana are written without spaces between words, so
+// each one is treated as a word of its own.
+fn is_cjk(c: char) -> bool {
+	matches!(c,
+		'\u{3040}'..='\u{309F}'
+		| '\u{3400}'..='\u{4DBF}'
+		| '\u{4E00}'..='\u{9FFF}'
+		| '\u{F900}'..='\u{FAFF}'
+		| '\u{20000}'..='\u{2FA1F}'
+		| '\u{30000}'..='\u{3134F}')
+}
+
+fn is_word_boundary(line: &str, at: usize) -> bool {
+	let before = line[..at].chars().next_back();
+	let after = line[at..].chars().next();
+	
+	match (before, after) {
+		(Some(a), Some(b)) => !(is_word_char(a) && is_word_char(b)) || is_cjk(a) || is_cjk(b),
+		_ => true,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn literal_spans() {
+		let matcher = Literal::new("ab");
+		assert_eq!(vec![Span::new(0, 2), Span::new(3, 5)], matcher.find_iter("ab ab"));
+	}
+	
+	#[test]
+	fn case_insensitive_spans() {
+		let matcher = CaseInsensitive::new("rust", CaseFolder::default());
+		assert_eq!(vec![Span::new(6, 10)], matcher.find_iter("Ünï RUST"));
+	}
+	
+	#[test]
+	fn several_patterns() {
+		let patterns = vec![String::from("fast"), String::from("safe"), String::from("fa")];
+		let config = Config { case_sensitive: true, ..Default::default() };
+		let matcher = from_config(&config, &patterns).unwrap();
+		
+		assert_eq!(
+			vec![Span::for_pattern(0, 4, 1), Span::for_pattern(6, 10, 0)],
+			matcher.find_iter("safe, fast")
+		);
+	}
+	
+	#[test]
+	fn several_patterns_ignoring_case() {
+		let patterns = vec![String::from("FAST"), String::from("p.ck")];
+		let config = Config { case_sensitive: false, ..Default::default() };
+		let matcher = from_config(&config, &patterns).unwrap();
+		
+		assert_eq!(vec![Span::for_pattern(6, 10, 0)], matcher.find_iter("safe, fast, pick"));
+	}
+	
+	#[test]
+	fn several_patterns_fold_like_one() {
+		let config = Config { case_sensitive: false, ..Default::default() };
+		let one = from_config(&config, &[String::from("straße")]).unwrap();
+		let two = from_config(&config, &[String::from("straße"), String::from("zzz")]).unwrap();
+		
+		for line in ["STRASSE", "Straße", "die strasse", "strase"] {
+			assert_eq!(one.find_iter(line), two.find_iter(line), "{}", line);
+		}
+		assert_eq!(vec![Span::for_pattern(4, 11, 0)], two.find_iter("die STRAßE"));
+		
+		let patterns = vec![String::from("a"), String::from("as")];
+		let matcher = from_config(&config, &patterns).unwrap();
+		assert_eq!(vec![Span::for_pattern(0, 1, 0)], matcher.find_iter("aß"));
+		
+		let turkic = Config { locale: crate::Locale::Turkic, ..config };
+		let patterns = vec![String::from("ISPARTA"), String::from("zzz")];
+		let matcher = from_config(&turkic, &patterns).unwrap();
+		assert!(matcher.is_match("ısparta"));
+		assert!(!matcher.is_match("isparta"));
+	}
+	
+	#[test]
+	fn whole_words() {
+		let config = Config { case_sensitive: true, word_regexp: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("duct")]).unwrap();
+		
+		assert!(!matcher.is_match("safe, fast, productive."));
+		assert_eq!(vec![Span::new(12, 16)], matcher.find_iter("productive (duct) tape"));
+		
+		let matcher = from_config(&config, &[String::from("中文")]).unwrap();
+		assert_eq!(vec![Span::new(6, 12)], matcher.find_iter("我们中文好"));
+	}
+	
+	#[test]
+	fn whole_words_ignoring_case() {
+		let config = Config { case_sensitive: false, word_regexp: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("RUST")]).unwrap();
+		
+		assert_eq!(vec![Span::new(6, 10)], matcher.find_iter("Trust rust"));
+	}
+	
+	#[test]
+	fn pattern_sets_find_all_in_one_pass() {
+		let patterns = vec![String::from("ab"), String::from("abc"), String::from("ß"), String::from("cd")];
+		let lines = ["ab abc ABCD", "STRASSE straße", "abcdab", "xaß abcd"];
+		for (case_sensitive, word_regexp) in [(true, true), (false, false), (false, true)] {
+			let config = Config { case_sensitive, word_regexp, ..Default::default() };
+			let matcher = from_config(&config, &patterns).unwrap();
+			for line in lines {
+				let mut one_by_one = Vec::new();
+				let mut from = 0;
+				while let Some(span) = matcher.find_at(line, from) {
+					one_by_one.push(span);
+					from = span.end;
+				}
+				assert_eq!(one_by_one, matcher.find_iter(line), "{}", line);
+			}
+		}
+	}
+	
+	#[test]
+	fn whole_words_in_alternations() {
+		let regex = Config { case_sensitive: true, word_regexp: true, regex: true, ..Default::default() };
+		let matcher = from_config(&regex, &[String::from("ab|abc")]).unwrap();
+		assert_eq!(vec![Span::new(0, 3)], matcher.find_iter("abc"));
+		assert_eq!(vec![Span::new(4, 7)], matcher.find_iter("foo abc bar"));
+		assert!(!matcher.is_match("abcd"));
+		
+		let patterns = vec![String::from("ab"), String::from("abc")];
+		let matcher = from_config(&regex, &patterns).unwrap();
+		assert_eq!(vec![Span::for_pattern(4, 7, 1)], matcher.find_iter("foo abc bar"));
+		
+		let patterns = vec![String::from("x"), String::from("x y"), String::from("x yz")];
+		for case_sensitive in [true, false] {
+			let config = Config { case_sensitive, word_regexp: true, ..Default::default() };
+			let matcher = from_config(&config, &patterns).unwrap();
+			assert_eq!(vec![Span::for_pattern(0, 1, 0)], matcher.find_iter("x yzq"));
+			assert_eq!(vec![Span::for_pattern(0, 3, 1), Span::for_pattern(5, 6, 0)], matcher.find_iter("x y, x"));
+		}
+		
+		let config = Config { case_sensitive: false, word_regexp: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("ab"), String::from("abc")]).unwrap();
+		assert_eq!(vec![Span::for_pattern(0, 3, 1)], matcher.find_iter("ABC"));
+		
+		let matcher = from_config(&regex, &[String::from("中文|中文好")]).unwrap();
+		assert_eq!(vec![Span::new(6, 12)], matcher.find_iter("我们中文好"));
+	}
+	
+	#[test]
+	fn whole_lines() {
+		let config = Config { case_sensitive: false, line_regexp: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("pick three.")]).unwrap();
+		assert!(matcher.is_match("Pick Three."));
+		assert!(!matcher.is_match("pick three. or four"));
+		
+		let config = Config { case_sensitive: true, line_regexp: true, regex: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("a|ab")]).unwrap();
+		assert!(matcher.is_match("ab"));
+		
+		let config = Config { case_sensitive: false, line_regexp: true, ..Default::default() };
+		let matcher = from_config(&config, &[String::from("a"), String::from("ab")]).unwrap();
+		assert!(matcher.is_match("AB"));
+		assert!(!matcher.is_match("abc"));
+	}
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use regex_automata::{meta, Anchored, Input, PatternID};
+
+use crate::{decode, BinaryFiles, Config, Matcher, MinigrepError, Span, Summary};
+
+// Produces the text that takes the place of each match. With regular
+// expressions, `$1` or `${name}` in the template refer to the capture groups
+// of the one that matched; otherwise the template is used as it is.
+pub struct Replacer {
+	template: String,
+	regex: Option<Regex>,
+	regex_set: Option<meta::Regex>,
+}
+
+impl Replacer {
+	pub fn new(template: &str, matcher: &dyn Matcher) -> Replacer {
+		Replacer {
+			template: template.to_string(),
+			regex: matcher.regex().cloned(),
+			regex_set: matcher.regex_set().cloned(),
+		}
+	}
+	
+	pub fn replacement(&self, line: &str, span: &Span) -> String {
+		let mut dst = String::new();
+		
+		if let Some(re) = &self.regex_set {
+			let pattern = PatternID::new(span.pattern).unwrap_or(PatternID::ZERO);
+			let input = Input::new(line).range(span.start..).anchored(Anchored::Pattern(pattern));
+			let mut caps = re.create_captures();
+			re.search_captures(&input, &mut caps);
+			if caps.get_match().is_some_and(|m| m.start() == span.start && m.end() == span.end) {
+				caps.interpolate_string_into(line, &self.template, &mut dst);
+				return dst;
+			}
+		}
+		if let Some(re) = &self.regex {
+			if let Some(caps) = re.captures_at(line, span.start) {
+				let whole = caps.get(0).unwrap();
+				if whole.start() == span.start && whole.end() == span.end {
+					caps.expand(&self.template, &mut dst);
+					return dst;
+				}
+			}
+		}
+		
+		dst.push_str(&self.template);
+		dst
+	}
+	
+	pub fn replace_line(&self, line: &str, spans: &[Span]) -> String {
+		let mut result = String::with_capacity(line.len());
+		let mut last = 0;
+		
+		for span in spans {
+			result.push_str(&line[last..span.start]);
+			result.push_str(&self.replacement(line, span));
+			last = span.end;
+		}
+		result.push_str(&line[last..]);
+		
+		result
+	}
+}
+
+// Rewrites each file with every match replaced, or with `dry_run` prints
+// the changes as a diff instead. A file is replaced by renaming a finished
+// temporary copy over it, so it is never left half written. If the config
+// has a backup suffix the original is kept under that suffix.
+pub fn edit_files(
+	config: &Config,
+	matcher: &dyn Matcher,
+	replacer: &Replacer,
+	files: &[PathBuf],
+	summary: &mut Summary,
+	out: &mut dyn Write,
+) -> io::Result<()> {
+	for file in files {
+		let result = if file == Path::new(crate::STDIN) {
+			let e = io::Error::new(io::ErrorKind::InvalidInput, "can't edit standard input in place");
+			Err(MinigrepError::io(crate::STDIN_LABEL, e))
+		} else {
+			edit_file(config, matcher, replacer, file, out)
+		};
+		
+		match result {
+			Ok(changed) => {
+				summary.files_searched += 1;
+				summary.matched_lines += changed;
+				if changed > 0 {
+					summary.files_matched += 1;
+				}
+			}
+			Err(MinigrepError::Io { source, .. }) if source.kind() == io::ErrorKind::BrokenPipe => {
+				return Err(source);
+			}
+			Err(e) => crate::report(summary, e),
+		}
+	}
+	
+	Ok(())
+}
+
+fn edit_file(
+	config: &Config,
+	matcher: &dyn Matcher,
+	replacer: &Replacer,
+	file: &Path,
+	out: &mut dyn Write,
+) -> Result<usize, MinigrepError> {
+	let bytes = fs::read(file).map_err(|e| MinigrepError::io(file, e))?;
+	// Compressed and binary files are left alone rather than rewritten as
+	// text, unless binary files are to be treated as text.
+	if decode::is_gzip(&bytes) || (config.binary_files != BinaryFiles::Text && decode::is_binary(&bytes)) {
+		return Ok(0);
+	}
+	let contents = String::from_utf8(bytes).map_err(|_| MinigrepError::Encoding { path: file.to_path_buf() })?;
+	
+	let mut edited = String::with_capacity(contents.len());
+	let mut changed = 0;
+	let mut printed_header = false;
+	
+	for (i, raw) in contents.split_inclusive('\n').enumerate() {
+		let line = raw.trim_end_matches('\n').trim_end_matches('\r');
+		let ending = &raw[line.len()..];
+		
+		let spans = matcher.find_iter(line);
+		if spans.is_empty() {
+			edited.push_str(raw);
+			continue;
+		}
+		
+		let new_line = replacer.replace_line(line, &spans);
+		if new_line != line {
+			changed += 1;
+			if config.dry_run {
+				let stdout_error = |e| MinigrepError::io("(standard output)", e);
+				if !printed_header {
+					writeln!(out, "--- {}", file.display()).map_err(stdout_error)?;
+					writeln!(out, "+++ {}", file.display()).map_err(stdout_error)?;
+					printed_header = true;
+				}
+				writeln!(out, "@@ -{0} +{0} @@", i + 1).map_err(stdout_error)?;
+				writeln!(out, "-{}", line).map_err(stdout_error)?;
+				writeln!(out, "+{}", new_line).map_err(stdout_error)?;
+			}
+		}
+		edited.push_str(&new_line);
+		edited.push_str(ending);
+	}
+	
+	if changed > 0 && !config.dry_run {
+		write_atomically(file, edited.as_bytes(), config.in_place.as_deref())
+			.map_err(|e| MinigrepError::io(file, e))?;
+	}
+	
+	Ok(changed)
+}
+
+fn write_atomically(file: &Path, contents: &[u8], backup_suffix: Option<&str>) -> io::Result<()> {
+	let name = file.file_name().unwrap_or_default().to_string_lossy();
+	let temp = file.with_file_name(format!(".{}.minigrep-{}.tmp", name, std::process::id()));
+	
+	let result = (|| {
+		let permissions = fs::metadata(file)?.permissions();
+		let mut temp_file = fs::File::create(&temp)?;
+		temp_file.write_all(contents)?;
+		temp_file.sync_all()?;
+		fs::set_permissions(&temp, permissions)?;
+		
+		if let Some(suffix) = backup_suffix.filter(|suffix| !suffix.is_empty()) {
+			let mut backup = file.as_os_str().to_os_string();
+			backup.push(suffix);
+			fs::copy(file, backup)?;
+		}
+		fs::rename(&temp, file)
+	})();
+	
+	if result.is_err() {
+		let _ = fs::remove_file(&temp);
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn config(regex: bool) -> Config {
+		Config { case_sensitive: true, regex, ..Default::default() }
+	}
+	
+	#[test]
+	fn replaces_capture_groups() {
+		let config = config(true);
+		let matcher = crate::matcher::from_config(&config, &[String::from(r"(\w+)@(\w+)")]).unwrap();
+		let replacer = Replacer::new("$2 at ${1}", matcher.as_ref());
+		let line = "mail bob@home or amy@work";
+		
+		assert_eq!("mail home at bob or work at amy", replacer.replace_line(line, &matcher.find_iter(line)));
+	}
+	
+	#[test]
+	fn replaces_capture_groups_of_each_pattern() {
+		let config = config(true);
+		let patterns = vec![String::from("(a)b"), String::from(r"z(\d)")];
+		let matcher = crate::matcher::from_config(&config, &patterns).unwrap();
+		let replacer = Replacer::new("[$1]", matcher.as_ref());
+		let line = "ab z7 ab";
+		
+		assert_eq!("[a] [7] [a]", replacer.replace_line(line, &matcher.find_iter(line)));
+	}
+	
+	#[test]
+	fn literal_template_without_regex() {
+		let config = config(false);
+		let matcher = crate::matcher::from_config(&config, &[String::from("$1")]).unwrap();
+		let replacer = Replacer::new("${1}", matcher.as_ref());
+		let line = "cost: $1";
+		
+		assert_eq!("cost: ${1}", replacer.replace_line(line, &matcher.find_iter(line)));
+	}
+	
+	#[test]
+	fn edits_in_place_with_backup() {
+		let dir = std::env::temp_dir().join("minigrep-replace-tests");
+		fs::create_dir_all(&dir).unwrap();
+		let file = dir.join("poem.txt");
+		fs::write(&file, "I'm nobody!\r\nWho are you?\n").unwrap();
+		
+		let config = Config { in_place: Some(String::from(".bak")), ..config(false) };
+		let matcher = crate::matcher::from_config(&config, &[String::from("nobody")]).unwrap();
+		let replacer = Replacer::new("somebody", matcher.as_ref());
+		let changed = edit_file(&config, matcher.as_ref(), &replacer, &file, &mut io::sink()).unwrap();
+		
+		assert_eq!(1, changed);
+		assert_eq!("I'm somebody!\r\nWho are you?\n", fs::read_to_string(&file).unwrap());
+		assert_eq!("I'm nobody!\r\nWho are you?\n", fs::read_to_string(dir.join("poem.txt.bak")).unwrap());
+	}
+	
+	#[test]
+	fn leaves_binary_and_compressed_files() {
+		let dir = std::env::temp_dir().join("minigrep-replace-binary");
+		fs::create_dir_all(&dir).unwrap();
+		let binary = dir.join("data.bin");
+		let gzip = dir.join("log.gz");
+		fs::write(&binary, b"nobody\0here\n").unwrap();
+		fs::write(&gzip, b"\x1F\x8B\x08nobody\n").unwrap();
+		
+		let config = Config { in_place: Some(String::new()), ..config(false) };
+		let matcher = crate::matcher::from_config(&config, &[String::from("nobody")]).unwrap();
+		let replacer = Replacer::new("somebody", matcher.as_ref());
+		for file in [&binary, &gzip] {
+			assert_eq!(0, edit_file(&config, matcher.as_ref(), &replacer, file, &mut io::sink()).unwrap());
+		}
+		assert_eq!(b"nobody\0here\n".to_vec(), fs::read(&binary).unwrap());
+		
+		let config = Config { binary_files: BinaryFiles::Text, ..config };
+		assert_eq!(1, edit_file(&config, matcher.as_ref(), &replacer, &binary, &mut io::sink()).unwrap());
+		assert_eq!(b"somebody\0here\n".to_vec(), fs::read(&binary).unwrap());
+	}
+}
@@ -0,0 +1,228 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::color::Colors;
+use crate::json;
+use crate::replace::Replacer;
+use crate::stream::Sink;
+use crate::{Config, Line, Summary};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputMode {
+	#[default]
+	Lines,
+	Count,
+	FilesWithMatches,
+	FilesWithoutMatch,
+	Json,
+}
+
+// Turns the search results for each file into output according to the
+// selected mode. The printer itself holds no per-file state so it can be
+// shared by the threads searching files in parallel; each file is written
+// through a `FilePrinter`.
+pub struct Printer<'a> {
+	config: &'a Config,
+	patterns: &'a [String],
+	show_names: bool,
+	colors: Colors,
+	replacer: Option<&'a Replacer>,
+}
+
+impl<'a> Printer<'a> {
+	pub fn new(
+		config: &'a Config,
+		patterns: &'a [String],
+		show_names: bool,
+		replacer: Option<&'a Replacer>,
+	) -> Printer<'a> {
+		let colors = Colors::from_env(config.color);
+		Printer { config, patterns, show_names, colors, replacer }
+	}
+	
+	// Starts the output for one file. `separate` says whether a group of
+	// lines has already been printed, so the first group of this file needs
+	// a separator in front of it.
+	pub fn file<'p, W: Write>(&'p self, path: &'p Path, out: W, separate: bool) -> FilePrinter<'p, W> {
+		FilePrinter { printer: self, path, out, printed_group: separate, error: None }
+	}
+	
+	// The line printed between groups, if groups are separated at all.
+	pub fn separator(&self) -> Option<String> {
+		if self.config.output_mode == OutputMode::Lines
+			&& (self.config.before_context > 0 || self.config.after_context > 0)
+		{
+			Some(Colors::paint(&self.colors.separator, "--"))
+		} else {
+			None
+		}
+	}
+	
+	pub fn summary(&self, out: &mut dyn Write, summary: &Summary, elapsed: Duration) -> io::Result<()> {
+		if self.config.output_mode == OutputMode::Json {
+			writeln!(out, "{}", json::summary(summary, elapsed))?;
+		}
+		Ok(())
+	}
+	
+	// Matching lines are printed with `:` after each prefix field, context lines with `-`.
+	fn line(&self, path: &Path, line: &Line) -> String {
+		let (m, sep) = match line {
+			Line::Match(m) => (m, ':'),
+			Line::Context(m) => (m, '-'),
+		};
+		
+		let mut prefix = String::new();
+		if self.show_names {
+			prefix.push_str(&self.path(path));
+			prefix.push_str(&self.sep(sep));
+		}
+		if self.config.line_number {
+			prefix.push_str(&Colors::paint(&self.colors.line_number, &m.line_number.to_string()));
+			prefix.push_str(&self.sep(sep));
+		}
+		if self.config.column && sep == ':' {
+			prefix.push_str(&Colors::paint(&self.colors.line_number, &m.column().to_string()));
+			prefix.push_str(&self.sep(sep));
+		}
+		if self.config.byte_offset {
+			prefix.push_str(&Colors::paint(&self.colors.byte_offset, &m.byte_offset.to_string()));
+			prefix.push_str(&self.sep(sep));
+		}
+		// Fuzzy matches show the smallest edit distance in the line as `~N`.
+		if self.config.fuzzy.is_some() {
+			if let Some(distance) = m.spans.iter().map(|span| span.distance).min() {
+				prefix.push_str(&Colors::paint(&self.colors.line_number, &format!("~{}", distance)));
+				prefix.push_str(&self.sep(sep));
+			}
+		}
+		
+		let mut text = String::with_capacity(m.line.len());
+		let mut last = 0;
+		for span in &m.spans {
+			text.push_str(&m.line[last..span.start]);
+			let matched = match self.replacer {
+				Some(replacer) => replacer.replacement(m.line, span),
+				None => m.line[span.start..span.end].to_string(),
+			};
+			text.push_str(&Colors::paint(&self.colors.matched, &matched));
+			last = span.end;
+		}
+		text.push_str(&m.line[last..]);
+		
+		prefix + &text
+	}
+	
+	fn path(&self, path: &Path) -> String {
+		Colors::paint(&self.colors.path, &path.display().to_string())
+	}
+	
+	fn sep(&self, sep: char) -> String {
+		Colors::paint(&self.colors.separator, &sep.to_string())
+	}
+}
+
+pub struct FilePrinter<'p, W> {
+	printer: &'p Printer<'p>,
+	path: &'p Path,
+	out: W,
+	printed_group: bool,
+	error: Option<io::Error>,
+}
+
+impl<'p, W: Write> FilePrinter<'p, W> {
+	pub fn begin(&mut self) {
+		if self.printer.config.output_mode == OutputMode::Json {
+			self.write(json::begin(self.path));
+		}
+	}
+	
+	// Stands in for the lines of a binary file that matches.
+	pub fn binary_matches(&mut self) {
+		if self.printer.config.output_mode == OutputMode::Lines {
+			let path = self.printer.path(self.path);
+			self.write(format!("Binary file {} matches", path));
+		}
+	}
+	
+	pub fn end(&mut self, count: usize) {
+		let printer = self.printer;
+		let path = self.path;
+		
+		match printer.config.output_mode {
+			OutputMode::Lines => {}
+			OutputMode::Count => {
+				if printer.show_names {
+					self.write(format!("{}{}{}", printer.path(path), printer.sep(':'), count));
+				} else {
+					self.write(count.to_string());
+				}
+			}
+			OutputMode::FilesWithMatches => {
+				if count > 0 {
+					self.write(printer.path(path));
+				}
+			}
+			OutputMode::FilesWithoutMatch => {
+				if count == 0 {
+					self.write(printer.path(path));
+				}
+			}
+			OutputMode::Json => self.write(json::end(path, count)),
+		}
+	}
+	
+	// Hands back the writer and whether any group of lines was printed, or
+	// the first error hit while writing.
+	pub fn finish(self) -> io::Result<(W, bool)> {
+		match self.error {
+			Some(e) => Err(e),
+			None => Ok((self.out, self.printed_group)),
+		}
+	}
+	
+	// Output errors are kept until `finish`, and stop the search.
+	fn write(&mut self, line: String) {
+		if self.error.is_none() {
+			if let Err(e) = writeln!(self.out, "{}", line) {
+				self.error = Some(e);
+			}
+		}
+	}
+}
+
+impl<'p, W: Write> Sink for FilePrinter<'p, W> {
+	fn group_start(&mut self) {
+		if self.printer.config.output_mode != OutputMode::Lines {
+			return;
+		}
+		if self.printed_group {
+			if let Some(separator) = self.printer.separator() {
+				self.write(separator);
+			}
+		}
+		self.printed_group = true;
+	}
+	
+	fn line(&mut self, line: &Line) -> bool {
+		match self.printer.config.output_mode {
+			OutputMode::Json => {
+				let fuzzy = self.printer.config.fuzzy.is_some();
+				self.write(json::line(self.path, line, self.printer.patterns, fuzzy));
+			}
+			OutputMode::Lines => self.write(self.printer.line(self.path, line)),
+			// Only the count is needed, so the first match of a file
+			// settles `-l` and `-L`.
+			OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch => {
+				return self.error.is_none() && matches!(line, Line::Context(_));
+			}
+			OutputMode::Count => {}
+		}
+		self.error.is_none()
+	}
+	
+	fn wants_spans(&self) -> bool {
+		matches!(self.printer.config.output_mode, OutputMode::Lines | OutputMode::Json)
+	}
+}
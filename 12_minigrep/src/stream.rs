@@ -0,0 +1,577 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+use memchr::memmem::Finder;
+use memchr::{memchr, memchr_iter, memrchr};
+
+use crate::{Line, Match, Matcher, Span};
+
+// How much input is gathered before skimming it for a literal.
+const SKIM_BLOCK: usize = 64 * 1024;
+
+// Receives the lines found by a `Searcher` in order. Each group of adjacent
+// lines is announced with `group_start` before its first line.
+pub trait Sink {
+	fn group_start(&mut self) {}
+	
+	// Returns whether the search should go on.
+	fn line(&mut self, line: &Line) -> bool;
+	
+	// Whether the lines need their spans. Sinks that only count them can
+	// say no, and are then handed lines without any.
+	fn wants_spans(&self) -> bool {
+		true
+	}
+}
+
+// Searches text line by line for the lines a matcher selects, together with
+// lines of context around them. With `lossy`, bytes that aren't UTF-8 are
+// replaced rather than being an error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Searcher {
+	invert: bool,
+	before: usize,
+	after: usize,
+	lossy: bool,
+}
+
+impl Searcher {
+	pub fn new() -> Searcher {
+		Searcher::default()
+	}
+	
+	// Selects the lines that do not match instead.
+	pub fn invert(mut self, yes: bool) -> Searcher {
+		self.invert = yes;
+		self
+	}
+	
+	pub fn before_context(mut self, lines: usize) -> Searcher {
+		self.before = lines;
+		self
+	}
+	
+	pub fn after_context(mut self, lines: usize) -> Searcher {
+		self.after = lines;
+		self
+	}
+	
+	pub fn lossy(mut self, yes: bool) -> Searcher {
+		self.lossy = yes;
+		self
+	}
+	
+	// Hands each selected line and its context to `sink` as soon as it is
+	// known, so memory use is bounded by the longest line and the `before`
+	// window rather than by the input size. Returns the number of selected
+	// lines.
+	pub fn search<R, S>(&self, matcher: &dyn Matcher, mut reader: R, sink: &mut S) -> io::Result<usize>
+	where
+		R: BufRead,
+		S: Sink + ?Sized,
+	{
+		if let Some(literal) = self.skimmable(matcher) {
+			return self.skim(matcher, literal.as_bytes(), reader, sink);
+		}
+		
+		let mut buf = Vec::new();
+		let mut state = State::new(*self);
+		
+		while let Some(line) = read_line(&mut reader, &mut buf, self.lossy)? {
+			if !state.line(matcher, &line, sink) {
+				break;
+			}
+		}
+		
+		Ok(state.count)
+	}
+	
+	// Every line a literal can select contains it, so without context lines
+	// the lines in between needn't be looked at. Line endings aren't part of
+	// the lines, so a literal with one is left to the line-by-line search.
+	fn skimmable<'m>(&self, matcher: &'m dyn Matcher) -> Option<&'m str> {
+		if self.invert || self.before > 0 || self.after > 0 {
+			return None;
+		}
+		matcher.required_literal().filter(|literal| !literal.is_empty() && !literal.contains(['\n', '\r']))
+	}
+	
+	// Searches blocks of whole lines for the literal at once, and only then
+	// finds the line around each occurrence for the matcher to check. Lines
+	// without the literal aren't decoded, so they can't fail as invalid UTF-8.
+	fn skim<R, S>(
+		&self,
+		matcher: &dyn Matcher,
+		literal: &[u8],
+		mut reader: R,
+		sink: &mut S,
+	) -> io::Result<usize>
+	where
+		R: BufRead,
+		S: Sink + ?Sized,
+	{
+		let finder = Finder::new(literal);
+		let exact = matcher.matches_literal();
+		let spans = sink.wants_spans();
+		let mut buf = Vec::with_capacity(SKIM_BLOCK * 2);
+		let mut state = State::new(*self);
+		// How much of the start of `buf` is known to hold no newline.
+		let mut checked = 0;
+		
+		loop {
+			let more = fill(&mut reader, &mut buf)?;
+			if more && buf.len() < SKIM_BLOCK {
+				continue;
+			}
+			let end = if more {
+				match memrchr(b'\n', &buf[checked..]) {
+					Some(i) => checked + i + 1,
+					None => {
+						checked = buf.len();
+						continue;
+					}
+				}
+			} else {
+				buf.len()
+			};
+			
+			let text = &buf[..end];
+			let mut pos = 0;
+			// Where the rest of `text` was checked to be UTF-8 from, and how
+			// much of it is, so that lines after a hit needn't be checked one
+			// by one. Only lines with a hit are decoded when it isn't.
+			let mut valid = (0, "");
+			while let Some(i) = finder.find(&text[pos..]) {
+				let hit = pos + i;
+				let start = memrchr(b'\n', &text[pos..hit]).map_or(pos, |i| pos + i + 1);
+				let line_end = memchr(b'\n', &text[hit..]).map_or(text.len(), |i| hit + i + 1);
+				
+				if start > pos {
+					state.skip(&text[pos..start]);
+				}
+				if line_end > valid.0 + valid.1.len() {
+					valid = (start, utf8_prefix(&text[start..]));
+				}
+				let line = if line_end <= valid.0 + valid.1.len() {
+					whole_line(&valid.1[start - valid.0..line_end - valid.0])
+				} else {
+					decode_line(&text[start..line_end], self.lossy)?
+				};
+				// A lossily decoded line no longer has the offsets of the input.
+				let more = if exact && matches!(line.text, Cow::Borrowed(_)) {
+					// The occurrence found is the first match of the line.
+					let spans = match spans {
+						true => literal_spans(&finder, &line.text, hit - start),
+						false => Vec::new(),
+					};
+					state.select(&line, true, spans, sink)
+				} else {
+					state.line(matcher, &line, sink)
+				};
+				if !more {
+					return Ok(state.count);
+				}
+				pos = line_end;
+			}
+			state.skip(&text[pos..]);
+			
+			buf.drain(..end);
+			checked = buf.len();
+			if !more {
+				return Ok(state.count);
+			}
+		}
+	}
+	
+	pub fn count<R: BufRead>(&self, matcher: &dyn Matcher, reader: R) -> io::Result<usize> {
+		self.search(matcher, reader, &mut Discard)
+	}
+	
+	// The lines `search` would find, read lazily as the iterator is advanced.
+	pub fn records<'m, R: BufRead>(&self, matcher: &'m dyn Matcher, reader: R) -> Records<'m, R> {
+		Records {
+			matcher,
+			reader,
+			buf: Vec::new(),
+			state: State::new(*self),
+			queue: VecDeque::new(),
+			done: false,
+		}
+	}
+}
+
+// A line found by `Searcher::records`, owning its text. Lines whose numbers
+// aren't consecutive belong to different groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+	pub line_number: usize,
+	pub byte_offset: usize,
+	pub line: String,
+	pub spans: Vec<Span>,
+	// Whether this is a line of context rather than a selected one.
+	pub context: bool,
+}
+
+impl Record {
+	pub fn from_line(line: &Line) -> Record {
+		let (m, context) = match line {
+			Line::Match(m) => (m, false),
+			Line::Context(m) => (m, true),
+		};
+		Record {
+			line_number: m.line_number,
+			byte_offset: m.byte_offset,
+			line: m.line.to_string(),
+			spans: m.spans.clone(),
+			context,
+		}
+	}
+}
+
+pub struct Records<'m, R> {
+	matcher: &'m dyn Matcher,
+	reader: R,
+	buf: Vec<u8>,
+	state: State,
+	queue: VecDeque<Record>,
+	done: bool,
+}
+
+impl<'m, R: BufRead> Iterator for Records<'m, R> {
+	type Item = io::Result<Record>;
+	
+	fn next(&mut self) -> Option<io::Result<Record>> {
+		loop {
+			if let Some(record) = self.queue.pop_front() {
+				return Some(Ok(record));
+			}
+			if self.done {
+				return None;
+			}
+			
+			match read_line(&mut self.reader, &mut self.buf, self.state.searcher.lossy) {
+				Ok(Some(line)) => {
+					self.state.line(self.matcher, &line, &mut self.queue);
+				}
+				Ok(None) => self.done = true,
+				Err(e) => {
+					self.done = true;
+					return Some(Err(e));
+				}
+			}
+		}
+	}
+}
+
+impl Sink for VecDeque<Record> {
+	fn line(&mut self, line: &Line) -> bool {
+		self.push_back(Record::from_line(line));
+		true
+	}
+}
+
+struct Discard;
+
+impl Sink for Discard {
+	fn line(&mut self, _: &Line) -> bool {
+		true
+	}
+	
+	fn wants_spans(&self) -> bool {
+		false
+	}
+}
+
+// A line as read, along with the number of bytes it took up.
+struct RawLine<'b> {
+	text: Cow<'b, str>,
+	read: usize,
+}
+
+// read_until carries a line that straddles two reads of the underlying
+// buffer over into the next fill.
+fn read_line<'b, R>(reader: &mut R, buf: &'b mut Vec<u8>, lossy: bool) -> io::Result<Option<RawLine<'b>>>
+where
+	R: BufRead,
+{
+	buf.clear();
+	if reader.read_until(b'\n', buf)? == 0 {
+		return Ok(None);
+	}
+	decode_line(buf, lossy).map(Some)
+}
+
+// `bytes` is a whole line, including its line ending if it has one.
+fn decode_line(bytes: &[u8], lossy: bool) -> io::Result<RawLine<'_>> {
+	let line = trim_line_end(bytes);
+	let text = match std::str::from_utf8(line) {
+		Ok(line) => Cow::Borrowed(line),
+		Err(_) if lossy => String::from_utf8_lossy(line),
+		Err(_) => {
+			let e = io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+			return Err(e);
+		}
+	};
+	
+	Ok(RawLine { text, read: bytes.len() })
+}
+
+// A whole line that is already known to be UTF-8.
+fn whole_line(line: &str) -> RawLine<'_> {
+	let text = line.strip_suffix('\n').unwrap_or(line);
+	let text = text.strip_suffix('\r').unwrap_or(text);
+	RawLine { text: Cow::Borrowed(text), read: line.len() }
+}
+
+// The longest start of `bytes` that is UTF-8.
+fn utf8_prefix(bytes: &[u8]) -> &str {
+	match std::str::from_utf8(bytes) {
+		Ok(text) => text,
+		Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+	}
+}
+
+// Moves the next block of input to the end of `buf`. Returns false at the
+// end of the input.
+fn fill<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<bool> {
+	let block = reader.fill_buf()?;
+	if block.is_empty() {
+		return Ok(false);
+	}
+	// Readers over memory hand out everything at once; taking it a block at
+	// a time keeps `buf` in the cache.
+	let len = block.len().min(SKIM_BLOCK);
+	buf.extend_from_slice(&block[..len]);
+	reader.consume(len);
+	Ok(true)
+}
+
+struct Pending {
+	line_number: usize,
+	byte_offset: usize,
+	line: String,
+	spans: Vec<Span>,
+}
+
+// Where a search is up to: the lines held back as possible before-context
+// and how many lines of after-context are still due.
+struct State {
+	searcher: Searcher,
+	pending: VecDeque<Pending>,
+	after_left: usize,
+	last_emitted: usize,
+	byte_offset: usize,
+	line_number: usize,
+	count: usize,
+}
+
+impl State {
+	fn new(searcher: Searcher) -> State {
+		State {
+			searcher,
+			pending: VecDeque::with_capacity(searcher.before),
+			after_left: 0,
+			last_emitted: 0,
+			byte_offset: 0,
+			line_number: 0,
+			count: 0,
+		}
+	}
+	
+	// Takes the next line of input. Returns false once the sink wants no more.
+	fn line<S: Sink + ?Sized>(&mut self, matcher: &dyn Matcher, raw: &RawLine, sink: &mut S) -> bool {
+		let line = raw.text.as_ref();
+		if sink.wants_spans() {
+			let spans = matcher.find_iter(line);
+			self.select(raw, !spans.is_empty(), spans, sink)
+		} else {
+			self.select(raw, matcher.is_match(line), Vec::new(), sink)
+		}
+	}
+	
+	// Takes the next line of input, once it is known whether it matches.
+	fn select<S>(&mut self, raw: &RawLine, matched: bool, spans: Vec<Span>, sink: &mut S) -> bool
+	where
+		S: Sink + ?Sized,
+	{
+		self.line_number += 1;
+		let line_number = self.line_number;
+		let byte_offset = self.byte_offset;
+		self.byte_offset += raw.read;
+		let line = raw.text.as_ref();
+		
+		if matched == self.searcher.invert {
+			if self.after_left > 0 {
+				self.after_left -= 1;
+				self.start_line(sink, line_number);
+				return sink.line(&Line::Context(Match { line_number, byte_offset, line, spans }));
+			} else if self.searcher.before > 0 {
+				let mut entry = if self.pending.len() == self.searcher.before {
+					self.pending.pop_front().unwrap()
+				} else {
+					Pending { line_number: 0, byte_offset: 0, line: String::new(), spans: Vec::new() }
+				};
+				entry.line_number = line_number;
+				entry.byte_offset = byte_offset;
+				entry.line.clear();
+				entry.line.push_str(line);
+				entry.spans = spans;
+				self.pending.push_back(entry);
+			}
+			return true;
+		}
+		
+		while let Some(entry) = self.pending.pop_front() {
+			self.start_line(sink, entry.line_number);
+			let context = Line::Context(Match {
+				line_number: entry.line_number,
+				byte_offset: entry.byte_offset,
+				line: &entry.line,
+				spans: entry.spans,
+			});
+			if !sink.line(&context) {
+				return false;
+			}
+		}
+		self.start_line(sink, line_number);
+		self.after_left = self.searcher.after;
+		self.count += 1;
+		sink.line(&Line::Match(Match { line_number, byte_offset, line, spans }))
+	}
+	
+	// Passes over lines that can't be selected.
+	fn skip(&mut self, lines: &[u8]) {
+		self.line_number += memchr_iter(b'\n', lines).count();
+		self.byte_offset += lines.len();
+	}
+	
+	fn start_line<S: Sink + ?Sized>(&mut self, sink: &mut S, line_number: usize) {
+		if self.last_emitted == 0 || self.last_emitted + 1 != line_number {
+			sink.group_start();
+		}
+		self.last_emitted = line_number;
+	}
+}
+
+// The occurrences of a literal in `line`, the first of which is at `first`.
+fn literal_spans(finder: &Finder, line: &str, first: usize) -> Vec<Span> {
+	let len = finder.needle().len();
+	let mut spans = vec![Span::new(first, first + len)];
+	let mut from = first + len;
+	while let Some(i) = finder.find(&line.as_bytes()[from..]) {
+		spans.push(Span::new(from + i, from + i + len));
+		from += i + len;
+	}
+	spans
+}
+
+fn trim_line_end(buf: &[u8]) -> &[u8] {
+	let line = buf.strip_suffix(b"\n").unwrap_or(buf);
+	line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::matcher::Literal;
+	use std::io::BufReader;
+	
+	#[test]
+	fn lines_across_buffer_boundaries() {
+		let contents = "first line\nsecond needle line\nthird line\nneedle again";
+		// A tiny buffer forces every line to span several reads.
+		let reader = BufReader::with_capacity(4, contents.as_bytes());
+		let matcher = Literal::new("needle");
+		
+		let found: Vec<(usize, usize, String)> = Searcher::new()
+			.records(&matcher, reader)
+			.map(|record| record.unwrap())
+			.map(|record| (record.line_number, record.byte_offset, record.line))
+			.collect();
+		
+		assert_eq!(
+			vec![
+				(2, 11, String::from("second needle line")),
+				(4, 41, String::from("needle again")),
+			],
+			found
+		);
+	}
+	
+	#[test]
+	fn skimming_agrees_with_line_search() {
+		let mut contents = String::new();
+		for i in 0..5000 {
+			contents.push_str(match i % 7 {
+				0 => "a needle here\r\n",
+				3 => "needles aren't whole words\n",
+				5 => "\n",
+				_ => "nothing to see on this line\n",
+			});
+		}
+		contents.push_str("last needle, needle");
+		let reader = || BufReader::with_capacity(100, contents.as_bytes());
+		
+		for (word_regexp, expected) in [(false, 1430), (true, 716)] {
+			let config = crate::Config { case_sensitive: true, word_regexp, ..Default::default() };
+			let matcher = crate::matcher::from_config(&config, &[String::from("needle")]).unwrap();
+			
+			let mut skimmed = VecDeque::new();
+			let count = Searcher::new().search(matcher.as_ref(), reader(), &mut skimmed).unwrap();
+			let records = Searcher::new().records(matcher.as_ref(), reader());
+			let by_line: Vec<Record> = records.map(|record| record.unwrap()).collect();
+			
+			assert_eq!(expected, count);
+			assert_eq!(by_line, Vec::from(skimmed));
+			assert_eq!(expected, Searcher::new().count(matcher.as_ref(), reader()).unwrap());
+		}
+	}
+	
+	#[test]
+	fn skimming_decodes_only_lines_with_hits() {
+		let matcher = Literal::new("needle");
+		let contents = b"a needle\n\xFF not text\nneedle b\n";
+		assert_eq!(2, Searcher::new().count(&matcher, &contents[..]).unwrap());
+		
+		let contents = b"a needle\nneedle \xFF\n";
+		assert!(Searcher::new().count(&matcher, &contents[..]).is_err());
+		assert_eq!(2, Searcher::new().lossy(true).count(&matcher, &contents[..]).unwrap());
+	}
+	
+	#[test]
+	fn skimming_lossy_lines_and_line_endings() {
+		let matcher = Literal::new("needle");
+		let contents = b"ok\n\xFFneedle here\n";
+		let mut found = VecDeque::new();
+		Searcher::new().lossy(true).search(&matcher, &contents[..], &mut found).unwrap();
+		assert_eq!(vec![Span::new(3, 9)], found[0].spans);
+		
+		let matcher = Literal::new("a\r");
+		let contents = b"a\r\nb\n";
+		let mut found = VecDeque::new();
+		assert_eq!(0, Searcher::new().search(&matcher, &contents[..], &mut found).unwrap());
+	}
+	
+	#[test]
+	fn sink_stops_search() {
+		struct First(Option<usize>);
+		
+		impl Sink for First {
+			fn line(&mut self, line: &Line) -> bool {
+				if let Line::Match(m) = line {
+					self.0 = Some(m.line_number);
+				}
+				false
+			}
+		}
+		
+		let matcher = Literal::new("x");
+		let contents = "a\nx1\nx2\n";
+		let mut first = First(None);
+		
+		let count = Searcher::new().search(&matcher, contents.as_bytes(), &mut first).unwrap();
+		assert_eq!((1, Some(2)), (count, first.0));
+		assert_eq!(2, Searcher::new().count(&matcher, contents.as_bytes()).unwrap());
+	}
+}
@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ignore::{self, Filter, Rules};
+use crate::MinigrepError;
+
+// `hidden` includes entries whose names start with a dot, and `ignore_files`
+// skips what `.gitignore`, `.ignore` and the global ignore file list.
+#[derive(Default)]
+pub struct WalkOptions {
+	pub max_depth: Option<usize>,
+	pub follow_links: bool,
+	pub hidden: bool,
+	pub ignore_files: bool,
+	pub filter: Filter,
+}
+
+// The state of one walk. `ignores` holds the rules of the directories being
+// walked, outermost first, each with the directory they apply to.
+struct Walk<'a> {
+	root: &'a Path,
+	options: &'a WalkOptions,
+	visited: HashSet<PathBuf>,
+	ignores: Vec<(PathBuf, Rules)>,
+	files: Vec<PathBuf>,
+	errors: &'a mut Vec<MinigrepError>,
+}
+
+// Lists the files under `root` in sorted order. Entries that can't be read
+// are added to `errors` and skipped.
+pub fn walk(root: &Path, options: &WalkOptions, errors: &mut Vec<MinigrepError>) -> Vec<PathBuf> {
+	let mut visited = HashSet::new();
+	let mut ignores = Vec::new();
+	
+	if options.follow_links {
+		match fs::canonicalize(root) {
+			Ok(real) => {
+				visited.insert(real);
+			}
+			Err(e) => {
+				errors.push(MinigrepError::io(root, e));
+				return Vec::new();
+			}
+		}
+	}
+	if options.ignore_files {
+		if let Some(path) = ignore::global_ignore_file() {
+			match Rules::from_file(&path) {
+				Ok(rules) => ignores.push((root.to_path_buf(), rules)),
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+				Err(e) => errors.push(MinigrepError::io(&path, e)),
+			}
+		}
+	}
+	
+	let mut walk = Walk { root, options, visited, ignores, files: Vec::new(), errors };
+	if let Err(e) = walk.dir(root, 0) {
+		walk.errors.push(MinigrepError::io(root, e));
+	}
+	
+	walk.files
+}
+
+impl<'a> Walk<'a> {
+	fn dir(&mut self, dir: &Path, depth: usize) -> io::Result<()> {
+		if let Some(max_depth) = self.options.max_depth {
+			if depth >= max_depth {
+				return Ok(());
+			}
+		}
+		
+		let mut entries = fs::read_dir(dir)?
+			.map(|entry| entry.map(|e| e.path()))
+			.collect::<io::Result<Vec<_>>>()?;
+		entries.sort();
+		
+		let pushed = self.options.ignore_files && self.read_ignore_files(dir);
+		let result = self.entries(entries, depth);
+		if pushed {
+			self.ignores.pop();
+		}
+		result
+	}
+	
+	// Pushes the rules of the ignore files in `dir`, if it has any.
+	fn read_ignore_files(&mut self, dir: &Path) -> bool {
+		let mut rules = String::new();
+		for name in ignore::IGNORE_FILES {
+			let path = dir.join(name);
+			match fs::read_to_string(&path) {
+				Ok(contents) => {
+					rules.push_str(&contents);
+					rules.push('\n');
+				}
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+				Err(e) => self.errors.push(MinigrepError::io(&path, e)),
+			}
+		}
+		
+		let rules = Rules::parse(&rules);
+		if rules.is_empty() {
+			return false;
+		}
+		self.ignores.push((dir.to_path_buf(), rules));
+		true
+	}
+	
+	fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+		if hidden && !self.options.hidden {
+			return true;
+		}
+		
+		let relative = path.strip_prefix(self.root).unwrap_or(path);
+		if self.options.filter.skips(relative, is_dir) {
+			return true;
+		}
+		
+		// Rules in deeper directories come later and win.
+		let ignored = self.ignores.iter().rev().find_map(|(base, rules)| {
+			rules.matched(path.strip_prefix(base).unwrap_or(path), is_dir)
+		});
+		ignored == Some(true)
+	}
+	
+	fn entries(&mut self, entries: Vec<PathBuf>, depth: usize) -> io::Result<()> {
+		for path in entries {
+			let meta = match fs::symlink_metadata(&path) {
+				Ok(meta) => meta,
+				Err(e) => {
+					self.errors.push(MinigrepError::io(&path, e));
+					continue;
+				}
+			};
+			
+			let file_type = if meta.file_type().is_symlink() {
+				if !self.options.follow_links {
+					continue;
+				}
+				match fs::metadata(&path) {
+					Ok(meta) => meta.file_type(),
+					Err(e) => {
+						self.errors.push(MinigrepError::io(&path, e));
+						continue;
+					}
+				}
+			} else {
+				meta.file_type()
+			};
+			
+			if file_type.is_dir() {
+				if self.is_ignored(&path, true) {
+					continue;
+				}
+				// Following links can lead back into a directory we are already in.
+				if self.options.follow_links {
+					match fs::canonicalize(&path) {
+						Ok(real) => {
+							if !self.visited.insert(real) {
+								continue;
+							}
+						}
+						Err(e) => {
+							self.errors.push(MinigrepError::io(&path, e));
+							continue;
+						}
+					}
+				}
+				if let Err(e) = self.dir(&path, depth + 1) {
+					self.errors.push(MinigrepError::io(&path, e));
+				}
+			} else if file_type.is_file() && !self.is_ignored(&path, false) {
+				self.files.push(path);
+			}
+		}
+		
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn make_tree(name: &str) -> PathBuf {
+		let root = std::env::temp_dir().join("minigrep-walk-tests").join(name);
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(root.join("a/b")).unwrap();
+		fs::write(root.join("top.txt"), "top").unwrap();
+		fs::write(root.join("a/mid.txt"), "mid").unwrap();
+		fs::write(root.join("a/b/deep.txt"), "deep").unwrap();
+		root
+	}
+	
+	#[test]
+	fn walks_recursively() {
+		let root = make_tree("recursive");
+		let options = WalkOptions { hidden: true, ..Default::default() };
+		
+		let files = walk(&root, &options, &mut Vec::new());
+		assert_eq!(
+			vec![root.join("a/b/deep.txt"), root.join("a/mid.txt"), root.join("top.txt")],
+			files
+		);
+	}
+	
+	#[test]
+	fn respects_max_depth() {
+		let root = make_tree("max_depth");
+		let options = WalkOptions { max_depth: Some(2), hidden: true, ..Default::default() };
+		
+		let files = walk(&root, &options, &mut Vec::new());
+		assert_eq!(vec![root.join("a/mid.txt"), root.join("top.txt")], files);
+	}
+	
+	#[test]
+	fn honours_ignore_files() {
+		let root = make_tree("ignore_files");
+		fs::write(root.join(".gitignore"), "b/\n*.txt\n").unwrap();
+		fs::write(root.join("a/.ignore"), "!mid.txt\n").unwrap();
+		fs::write(root.join(".hidden.md"), "hidden").unwrap();
+		fs::write(root.join("notes.md"), "notes").unwrap();
+		let options = WalkOptions { ignore_files: true, ..Default::default() };
+		
+		let files = walk(&root, &options, &mut Vec::new());
+		assert_eq!(vec![root.join("a/mid.txt"), root.join("notes.md")], files);
+	}
+}